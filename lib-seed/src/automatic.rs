@@ -1,4 +1,4 @@
-use maratona_animeitor_rust::data;
+use maratona_animeitor_rust::{data, events};
 use seed::{prelude::*, *};
 
 use crate::requests::*;
@@ -9,8 +9,7 @@ extern crate rand;
 
 fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
     orders.subscribe(Msg::UrlChanged);
-    orders.skip().send_msg(Msg::Reload);
-    orders.skip().stream(streams::interval(30_000, || Msg::Reload));
+    orders.skip().send_msg(Msg::Connect);
 
     Model {
         source : get_source(&url),
@@ -18,6 +17,9 @@ fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
         url_filter : get_url_filter(&url),
         contest: data::ContestFile::dummy(),
         runs: data::RunsFile::empty(),
+        awards: data::Awards::default(),
+        next_center: None,
+        updates: None,
     }
 }
 
@@ -27,15 +29,23 @@ struct Model {
     url_filter: Option<Vec<String>>,
     contest : data::ContestFile,
     runs: data::RunsFile,
+    awards: data::Awards,
+    /// Team of the latest change pushed, centered once the new scoreboard arrives.
+    next_center: Option<String>,
+    updates: Option<WebSocket>,
 }
 
 enum Msg {
     UrlChanged(subs::UrlChanged),
+    Connect,
+    Disconnected,
+    Pushed(events::Update),
     Reload,
     Recenter,
     Fetched(
         fetch::Result<data::RunsFile>,
         fetch::Result<data::ContestFile>),
+    FetchedAwards(fetch::Result<data::Awards>),
 }
 
 async fn fetch_all(source : Option<String>) -> Msg {
@@ -44,6 +54,10 @@ async fn fetch_all(source : Option<String>) -> Msg {
     Msg::Fetched(r, c)
 }
 
+async fn fetch_all_awards(source : Option<String>) -> Msg {
+    Msg::FetchedAwards(fetch_awards(&source).await)
+}
+
 fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
     match msg {
         Msg::UrlChanged(subs::UrlChanged(url)) => {
@@ -55,8 +69,32 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::Recenter => {
             model.center = None;
         },
+        Msg::Connect => {
+            model.updates = subscribe_updates(&model.source, orders, Msg::Pushed, || Msg::Disconnected);
+            if model.updates.is_none() {
+                orders.send_msg(Msg::Disconnected);
+            }
+            // catches up with the updates missed while disconnected
+            orders.send_msg(Msg::Reload);
+        },
+        Msg::Disconnected => {
+            model.updates = None;
+            orders.perform_cmd(cmds::timeout(5_000, || Msg::Connect));
+        },
+        Msg::Pushed(update) => {
+            if !update.events.is_empty() {
+                let changed = update.events.iter().rev()
+                    .find(|e| e.event.changes_score())
+                    .and_then(|e| e.event.team_login());
+                if let Some(login) = changed {
+                    model.next_center = Some(login.to_string());
+                }
+                orders.send_msg(Msg::Reload);
+            }
+        },
         Msg::Reload => {
             orders.skip().perform_cmd(fetch_all(model.source.clone()));
+            orders.perform_cmd(fetch_all_awards(model.source.clone()));
         },
         Msg::Fetched(Ok(runs), Ok(contest)) => {
             
@@ -65,24 +103,19 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
 
             log!("received n runs: {}", model.runs.len());
             
+            // runs after the freeze come without their verdicts, and are shown as pending
             for r in model.runs.sorted() {
-                if r.time < model.contest.score_freeze_time {
-                    let r = model.contest.apply_run(r);
-                    match r {
-                        Ok(()) => (),
-                        Err(e) => log!("run error:", e),
-                    }
+                let r = model.contest.apply_run(r);
+                match r {
+                    Ok(()) => (),
+                    Err(e) => log!("run error:", e),
                 }
             }
             
-            let old_contest = model.contest.clone();
             model.contest.recalculate_placement().unwrap();
-            for (t1, t2) in model.contest.teams.values().zip(old_contest.teams.values()) {
-                if t1.placement < t2.placement {
-                    model.center = Some(t1.login.clone());
-                    break;
-                }
-            }            
+
+            // centers on the latest change of the scoreboard pushed since the last reload
+            model.center = model.next_center.take();
             
             orders.perform_cmd(cmds::timeout(10_000, move|| Msg::Recenter));
             // log!("fetched runs and contest!", model.contest);
@@ -93,12 +126,18 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::Fetched(_, Err(e)) => {
             log!("failed fetching contest: ", e);
         },
+        Msg::FetchedAwards(Ok(awards)) => {
+            model.awards = awards;
+        },
+        Msg::FetchedAwards(Err(e)) => {
+            log!("failed fetching awards: ", e);
+        },
 
     }
 }
 
 fn view(model: &Model) -> Node<Msg> {
-    views::view_scoreboard(&model.contest, &model.center, &model.url_filter)
+    views::view_scoreboard(&model.contest, &model.awards, &model.center, &model.url_filter)
 }
 
 pub fn start(e : impl GetElement) {
@@ -12,6 +12,10 @@ pub fn get_secret(url : &Url) -> String {
     url.search().get("secret").unwrap().first().unwrap().to_string()
 }
 
+pub fn get_optional_secret(url : &Url) -> Option<String> {
+    url.search().get("secret").and_then(|v| v.first()).map(|s| s.to_string())
+}
+
 pub fn get_url_filter(url : &Url) -> Option<Vec<String>> {
     url.search().get("filter").cloned()
 }
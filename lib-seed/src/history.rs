@@ -0,0 +1,147 @@
+use maratona_animeitor_rust::{data, events};
+use seed::{prelude::*, *};
+use crate::requests::*;
+use crate::helpers::*;
+
+const WIDTH : f64 = 1000.0;
+const HEIGHT : f64 = 500.0;
+const COLORS : [&str; 8] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+];
+
+fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
+    orders.skip().send_msg(Msg::Connect);
+    Model {
+        source : get_source(&url),
+        teams : get_url_teams(&url),
+        contest : data::ContestFile::dummy(),
+        history : data::RankHistory::new(),
+        updates : None,
+    }
+}
+
+struct Model {
+    source : Option<String>,
+    teams : Vec<String>,
+    contest : data::ContestFile,
+    history : data::RankHistory,
+    updates : Option<WebSocket>,
+}
+
+enum Msg {
+    Connect,
+    Disconnected,
+    Pushed(events::Update),
+    Reset,
+    Fetched(fetch::Result<data::RankHistory>, fetch::Result<Box<data::ContestFile>>),
+}
+
+fn get_url_teams(url : &Url) -> Vec<String> {
+    url.search().get("team")
+        .map(|v| v.iter().flat_map(|t| t.split(',')).map(|t| t.to_string()).collect())
+        .unwrap_or_default()
+}
+
+async fn fetch_all(source : Option<String>, teams : Vec<String>) -> Msg {
+    let h = fetch_history(&source, &teams).await;
+    let c = fetch_contest(&source).await.map(Box::new);
+    Msg::Fetched(h, c)
+}
+
+fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
+    match msg {
+        Msg::Fetched(Ok(history), Ok(contest)) => {
+            model.history = history;
+            model.contest = *contest;
+        },
+        Msg::Fetched(Err(e), _) => {
+            log!("fetched history error!", e)
+        },
+        Msg::Fetched(_, Err(e)) => {
+            log!("fetched contest error!", e)
+        },
+        Msg::Connect => {
+            model.updates = subscribe_updates(&model.source, orders, Msg::Pushed, || Msg::Disconnected);
+            if model.updates.is_none() {
+                orders.send_msg(Msg::Disconnected);
+            }
+            orders.send_msg(Msg::Reset);
+        },
+        Msg::Disconnected => {
+            model.updates = None;
+            orders.perform_cmd(cmds::timeout(5_000, || Msg::Connect));
+        },
+        Msg::Pushed(update) => {
+            if !update.events.is_empty() {
+                orders.send_msg(Msg::Reset);
+            }
+        },
+        Msg::Reset => {
+            orders.skip().perform_cmd( fetch_all(model.source.clone(), model.teams.clone()) );
+        }
+    }
+}
+
+/// Steps of the line: the placement holds until the next point.
+/// Unofficial teams have no placement, and are not drawn.
+fn line_points(points : &[data::RankPoint], max_time : data::Minutes, max_placement : usize) -> String {
+    let x = |t : data::Minutes| WIDTH * t.0 as f64 / max_time.0.max(1) as f64;
+    let y = |p : usize| HEIGHT * (p.max(1) - 1) as f64 / (max_placement.max(2) - 1) as f64;
+
+    let mut coords = Vec::new();
+    for (i, p) in points.iter().enumerate() {
+        if p.placement == 0 {
+            continue;
+        }
+        if i > 0 && points[i - 1].placement != 0 {
+            coords.push(format!("{:.1},{:.1}", x(p.time), y(points[i - 1].placement)));
+        }
+        coords.push(format!("{:.1},{:.1}", x(p.time), y(p.placement)));
+    }
+    if let Some(last) = points.last().filter(|p| p.placement != 0) {
+        coords.push(format!("{:.1},{:.1}", x(max_time), y(last.placement)));
+    }
+    coords.join(" ")
+}
+
+fn view(model: &Model) -> Node<Msg> {
+    let max_time = model.contest.maximum_time;
+    let max_placement = model.history.values()
+        .flat_map(|points| points.iter().map(|p| p.placement))
+        .max()
+        .unwrap_or(1);
+
+    div![
+        C!["rankhistory"],
+        svg![
+            attrs!{
+                At::ViewBox => format!("0 0 {} {}", WIDTH, HEIGHT),
+                At::Width => "100%",
+            },
+            model.teams.iter().enumerate()
+                .filter_map(|(i, login)| model.history.get(login).map(|points| (i, points)))
+                .map(|(i, points)| polyline![
+                    attrs!{
+                        At::Points => line_points(points, max_time, max_placement),
+                        At::Fill => "none",
+                        At::Stroke => COLORS[i % COLORS.len()],
+                        At::StrokeWidth => 3,
+                    }
+                ]),
+        ],
+        div![
+            C!["rankhistorylegend"],
+            model.teams.iter().enumerate().map(|(i, login)| {
+                let team = model.contest.teams.get(login);
+                span![
+                    style!{St::Color => COLORS[i % COLORS.len()]},
+                    team.map(|t| format!("{} {}", t.escola, t.name)).unwrap_or(login.clone()),
+                ]
+            }),
+        ],
+    ]
+}
+
+pub fn start(e : impl GetElement) {
+    App::start(e, init, update, view);
+}
@@ -11,6 +11,8 @@ mod timer;
 mod reveleitor;
 mod navigation;
 mod sede;
+mod history;
+mod statspanel;
 
 #[wasm_bindgen(start)]
 pub fn start() {
@@ -34,6 +36,8 @@ pub fn start() {
                         "runspanel" => runs::start(root_element),
                         "timerpanel" => timer::start(root_element),
                         "sedepanel" => sede::start(root_element),
+                        "rankhistory" => history::start(root_element),
+                        "statspanel" => statspanel::start(root_element),
                         s => log!("wrong app!:", s)
                     };
             }
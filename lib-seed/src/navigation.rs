@@ -1,32 +1,46 @@
 // use maratona_animeitor_rust::data;
 use maratona_animeitor_rust::configdata;
-use maratona_animeitor_rust::config;
 use seed::{prelude::*, *};
 
-// use crate::requests::*;
-// use crate::helpers::*;
+use crate::requests::*;
+use crate::helpers::*;
 // use crate::views;
 
 extern crate rand;
 
-fn init(_: Url, orders: &mut impl Orders<Msg>) -> Model {
+fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
     orders.subscribe(Msg::UrlChanged);
+    orders.perform_cmd( fetch_all(get_source(&url)) );
     Model {
+        sedes: Vec::new(),
     }
 }
 
 struct Model {
+    sedes : Vec<configdata::Sede>,
 }
 
 enum Msg {
-    UrlChanged(subs::UrlChanged)
+    UrlChanged(subs::UrlChanged),
+    Fetched(fetch::Result<configdata::Contest>),
 }
 
-fn update(msg: Msg, _: &mut Model, _: &mut impl Orders<Msg>) {
+async fn fetch_all(source :Option<String>) -> Msg {
+    let f = fetch_sedes(&source).await;
+    Msg::Fetched(f)
+}
+
+fn update(msg: Msg, model: &mut Model, _: &mut impl Orders<Msg>) {
     match msg {
         Msg::UrlChanged(subs::UrlChanged(url)) => {
             url.go_and_load();
-        }
+        },
+        Msg::Fetched(Ok(contest)) => {
+            model.sedes = contest.sedes;
+        },
+        Msg::Fetched(Err(e)) => {
+            log!("fetched sedes error!", e)
+        },
     }
 }
 
@@ -42,9 +56,9 @@ fn build_url_filter(sede : &configdata::Sede) -> String {
     ])).to_string()
 }
 
-fn view(_: &Model) -> Node<Msg> {
+fn view(model: &Model) -> Node<Msg> {
     div![C!["sedesnavigation"],
-        config::contest().sedes.iter().map( |sede| {
+        model.sedes.iter().map( |sede| {
             span![
                 C!["sedeslink"],
                 a![attrs!{At::Href=>build_url_filter(&sede), At::Target=>"principal"}, &sede.name],
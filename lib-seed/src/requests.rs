@@ -1,5 +1,14 @@
-use seed::prelude::*;
-use maratona_animeitor_rust::data;
+use seed::{prelude::*, *};
+use maratona_animeitor_rust::{data, configdata, events, stats};
+use std::collections::BTreeMap;
+
+/// Display order, teams, problems and first accepted run of each problem.
+pub type Scoreboard = (
+    Vec<String>,
+    BTreeMap<String, data::Team>,
+    Vec<data::ProblemInfo>,
+    BTreeMap<String, data::FirstSolve>,
+);
 
 fn prepend(url : &str, source:&Option<String>) -> String {
     source.clone().map( |s| format!("/{}{}", s, url) ).unwrap_or(url.to_string())
@@ -53,3 +62,95 @@ pub async fn fetch_time_file(source :&Option<String>) -> fetch::Result<data::Tim
         .json()
         .await
 }
+
+pub async fn fetch_sedes(source :&Option<String>) -> fetch::Result<configdata::Contest> {
+    Request::new(prepend("/sedes", source))
+        .fetch()
+        .await?
+        .check_status()?
+        .json()
+        .await
+}
+
+pub async fn fetch_awards(source :&Option<String>) -> fetch::Result<data::Awards> {
+    Request::new(prepend("/awards", source))
+        .fetch()
+        .await?
+        .check_status()?
+        .json()
+        .await
+}
+
+pub async fn fetch_awards_secret(source :&Option<String>, secret : &String) -> fetch::Result<data::Awards> {
+    Request::new(prepend(format!("/awards_{}", secret).as_str(), source))
+        .fetch()
+        .await?
+        .check_status()?
+        .json()
+        .await
+}
+
+pub async fn fetch_score_at(source :&Option<String>, at : data::Minutes, secret : &Option<String>) -> fetch::Result<Scoreboard> {
+    let query = match secret {
+        None => format!("/score?at={}", at.0),
+        Some(secret) => format!("/score_{}?at={}", secret, at.0),
+    };
+    Request::new(prepend(&query, source))
+        .fetch()
+        .await?
+        .check_status()?
+        .json()
+        .await
+}
+
+pub async fn fetch_history(source :&Option<String>, teams : &[String]) -> fetch::Result<data::RankHistory> {
+    let teams : Vec<String> = teams.iter().map(Url::encode_uri_component).collect();
+    Request::new(prepend(&format!("/history?team={}", teams.join(",")), source))
+        .fetch()
+        .await?
+        .check_status()?
+        .json()
+        .await
+}
+
+pub async fn fetch_stats(source :&Option<String>, sede : &Option<String>, secret : &Option<String>) -> fetch::Result<stats::ContestStats> {
+    let path = match secret {
+        None => "/stats".to_string(),
+        Some(secret) => format!("/stats_{}", secret),
+    };
+    let query = match sede {
+        None => path,
+        Some(sede) => format!("{}?sede={}", path, sede),
+    };
+    Request::new(prepend(&query, source))
+        .fetch()
+        .await?
+        .check_status()?
+        .json()
+        .await
+}
+
+/// Opens the socket where the server pushes an `Update` every time it refreshes its data.
+/// `closed` is sent when the connection drops, so the app can reconnect.
+pub fn subscribe_updates<Ms: 'static>(
+    source :&Option<String>,
+    orders : &impl Orders<Ms>,
+    pushed : impl FnOnce(events::Update) -> Ms + Clone + 'static,
+    closed : impl FnOnce() -> Ms + Clone + 'static,
+) -> Option<WebSocket> {
+    let location = window().location();
+    let scheme = if location.protocol().ok()? == "https:" { "wss" } else { "ws" };
+    let url = format!("{}://{}{}", scheme, location.host().ok()?, prepend("/updates", source));
+    WebSocket::builder(url, orders)
+        .on_message(move |message: WebSocketMessage| match message.json() {
+            Ok(update) => Some(pushed(update)),
+            Err(e) => {
+                log!("invalid update: ", e);
+                None
+            },
+        })
+        .on_close(move |_| closed())
+        .build_and_open()
+        .map_err(|e| log!("failed subscribing to updates: ", e))
+        .ok()
+}
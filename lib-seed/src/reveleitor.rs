@@ -17,6 +17,7 @@ fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
         contest: data::ContestFile::dummy(),
         runs: data::RunsFile::empty(),
         runs_queue : data::RunsQueue::empty(),
+        awards: data::Awards::default(),
         // current_run: 0,
         center: None,
         // lock_frozen : true,
@@ -30,6 +31,8 @@ struct Model {
     contest : data::ContestFile,
     runs: data::RunsFile,
     runs_queue : data::RunsQueue,
+    /// Awards of the final scoreboard.
+    awards : data::Awards,
     // current_run: usize,
     center : Option<String>,
     // lock_frozen : bool,
@@ -48,13 +51,15 @@ enum Msg {
     Reset,
     Fetched(
         fetch::Result<data::RunsFile>,
-        fetch::Result<data::ContestFile>),
+        fetch::Result<data::ContestFile>,
+        fetch::Result<data::Awards>),
 }
 
 async fn fetch_all(source :Option<String>, secret : String) -> Msg {
     let r = fetch_allruns_secret(&source, &secret).await;
     let c = fetch_contest(&source).await;
-    Msg::Fetched(r, c)
+    let a = fetch_awards_secret(&source, &secret).await;
+    Msg::Fetched(r, c, a)
 }
 
 fn apply_all_runs_before_frozen(model: &mut Model) {
@@ -139,11 +144,12 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             model.button_disabled = false;
 
         },
-        Msg::Fetched(Ok(runs), Ok(contest)) => {
+        Msg::Fetched(Ok(runs), Ok(contest), Ok(awards)) => {
             // model.current_run = 0;
             model.center = None;
             model.runs = runs;
             model.contest = contest;
+            model.awards = awards;
 
             // log!("runs received in reveleitor:", model.runs.len());
             apply_all_runs_before_frozen(model);
@@ -154,12 +160,15 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             // log!("run queue: ", model.runs_queue);
             model.button_disabled = false;
         },
-        Msg::Fetched(Err(e), _) => {
+        Msg::Fetched(Err(e), _, _) => {
             log!("fetched runs error!", e)
         },
-        Msg::Fetched(_, Err(e)) => {
+        Msg::Fetched(_, Err(e), _) => {
             log!("fetched contest error!", e)
         },
+        Msg::Fetched(_, _, Err(e)) => {
+            log!("fetched awards error!", e)
+        },
         Msg::Reset => {
             model.button_disabled = true;
             orders.skip().perform_cmd( fetch_all(model.source.clone(), model.secret.clone()) );
@@ -186,7 +195,7 @@ fn view(model: &Model) -> Node<Msg> {
         ],
         div![
             style!{St::Position => "relative", St::Top => px(60)},
-            views::view_scoreboard(&model.contest, &model.center, &None),
+            views::view_scoreboard(&model.contest, &model.awards, &model.center, &None),
         ]
     ]
 }
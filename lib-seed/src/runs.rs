@@ -1,4 +1,4 @@
-use maratona_animeitor_rust::data;
+use maratona_animeitor_rust::{data, events};
 use seed::{prelude::*, *};
 use crate::views;
 use crate::requests::*;
@@ -9,12 +9,13 @@ extern crate rand;
 
 fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
     orders.subscribe(Msg::UrlChanged);
-    orders.skip().send_msg(Msg::Reset);
-    orders.stream(streams::interval(30_000, || Msg::Reset));
+    orders.skip().send_msg(Msg::Connect);
     Model {
         source : get_source(&url),
         url_filter : get_url_filter(&url),
         runs: Vec::new(),
+        awards: data::Awards::default(),
+        updates: None,
     }
 }
 
@@ -22,17 +23,26 @@ struct Model {
     url_filter : Option<Vec<String>>,
     source : Option<String>,
     runs: Vec<data::RunsPanelItem>,
+    awards: data::Awards,
+    updates: Option<WebSocket>,
 }
 
 enum Msg {
+    Connect,
+    Disconnected,
+    Pushed(events::Update),
     Reset,
     UrlChanged(subs::UrlChanged),
     Fetched(fetch::Result<Vec<data::RunsPanelItem>>),
+    FetchedAwards(fetch::Result<data::Awards>),
 }
 
 async fn fetch_all(source :Option<String>) -> Msg {
-    let f= fetch_runspanel(&source).await;
-    Msg::Fetched(f)
+    Msg::Fetched(fetch_runspanel(&source).await)
+}
+
+async fn fetch_all_awards(source :Option<String>) -> Msg {
+    Msg::FetchedAwards(fetch_awards(&source).await)
 }
 
 fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
@@ -60,8 +70,31 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::Fetched(Err(e)) => {
             log!("fetched runs error!", e)
         },
+        Msg::FetchedAwards(Ok(awards)) => {
+            model.awards = awards;
+        },
+        Msg::FetchedAwards(Err(e)) => {
+            log!("fetched awards error!", e)
+        },
+        Msg::Connect => {
+            model.updates = subscribe_updates(&model.source, orders, Msg::Pushed, || Msg::Disconnected);
+            if model.updates.is_none() {
+                orders.send_msg(Msg::Disconnected);
+            }
+            orders.send_msg(Msg::Reset);
+        },
+        Msg::Disconnected => {
+            model.updates = None;
+            orders.perform_cmd(cmds::timeout(5_000, || Msg::Connect));
+        },
+        Msg::Pushed(update) => {
+            if !update.events.is_empty() {
+                orders.send_msg(Msg::Reset);
+            }
+        },
         Msg::Reset => {
             orders.skip().perform_cmd( fetch_all(model.source.clone()) );
+            orders.perform_cmd( fetch_all_awards(model.source.clone()) );
         }
     }
 }
@@ -69,7 +102,7 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
 fn get_answer(t : &data::Answer) -> &str {
     match t {
         data::Answer::Yes(_)  => "answeryes",
-        data::Answer::No   => "answerno",
+        data::Answer::No(_) => "answerno",
         data::Answer::Wait => "answerwait",
         _                  => "answererror"
     }
@@ -84,14 +117,18 @@ fn view(model: &Model) -> Node<Msg> {
             style!{ 
                 St::Top => format!("calc(var(--row-height) * {} + var(--root-top))", i),
             },
-            div![C!["cell", "colocacao", views::get_color(r.placement)], r.placement],
+            views::placement_cell(r.placement, views::medal_class(&model.awards, &r.team_login)),
             div![
                 C!["cell", "time"],
                 div![C!["nomeEscola"], &r.escola],
                 div![C!["nomeTime"], &r.team_name],
             ],
-            div![C!["cell", "problema"], &r.problem],
+            div![C!["cell", "problema"], IF!(r.color != 0 => C!["primeiro"]), &r.problem],
             div![C!["cell", "resposta", get_answer(&r.result)]],
+            match &r.result {
+                data::Answer::No(verdict) => div![C!["cell", "veredito"], verdict.abbreviation()],
+                _ => empty![],
+            },
         ]
     })]
 }
@@ -0,0 +1,116 @@
+use maratona_animeitor_rust::{events, stats};
+use seed::{prelude::*, *};
+use crate::requests::*;
+use crate::helpers::*;
+
+fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
+    orders.skip().send_msg(Msg::Connect);
+    Model {
+        source : get_source(&url),
+        sede : url.search().get("sede").and_then(|v| v.first()).cloned(),
+        secret : get_optional_secret(&url),
+        stats : None,
+        updates : None,
+    }
+}
+
+struct Model {
+    source : Option<String>,
+    sede : Option<String>,
+    secret : Option<String>,
+    stats : Option<stats::ContestStats>,
+    updates : Option<WebSocket>,
+}
+
+enum Msg {
+    Connect,
+    Disconnected,
+    Pushed(events::Update),
+    Reset,
+    Fetched(fetch::Result<stats::ContestStats>),
+}
+
+async fn fetch_all(source : Option<String>, sede : Option<String>, secret : Option<String>) -> Msg {
+    let s = fetch_stats(&source, &sede, &secret).await;
+    Msg::Fetched(s)
+}
+
+fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
+    match msg {
+        Msg::Fetched(Ok(stats)) => {
+            model.stats = Some(stats);
+        },
+        Msg::Fetched(Err(e)) => {
+            log!("fetched stats error!", e)
+        },
+        Msg::Connect => {
+            model.updates = subscribe_updates(&model.source, orders, Msg::Pushed, || Msg::Disconnected);
+            if model.updates.is_none() {
+                orders.send_msg(Msg::Disconnected);
+            }
+            orders.send_msg(Msg::Reset);
+        },
+        Msg::Disconnected => {
+            model.updates = None;
+            orders.perform_cmd(cmds::timeout(5_000, || Msg::Connect));
+        },
+        Msg::Pushed(update) => {
+            if !update.events.is_empty() {
+                orders.send_msg(Msg::Reset);
+            }
+        },
+        Msg::Reset => {
+            orders.skip().perform_cmd( fetch_all(model.source.clone(), model.sede.clone(), model.secret.clone()) );
+        }
+    }
+}
+
+fn optional_time(t : Option<i64>) -> String {
+    t.map(|t| t.to_string()).unwrap_or("-".to_string())
+}
+
+fn view_problems(stats : &stats::ContestStats) -> Node<Msg> {
+    let buckets = stats.problems.first().map(|p| p.solves_per_bucket.len()).unwrap_or(0);
+    table![
+        C!["statstable"],
+        tr![
+            th!["Problema"], th!["Tentativas"], th!["Aceitas"], th!["Taxa"], th!["Times"],
+            th!["Primeira"], th!["Última"],
+            (0..buckets).map(|b| th![format!("{}-{}", b as i64 * stats::BUCKET_MINUTES, (b as i64 + 1) * stats::BUCKET_MINUTES)]),
+        ],
+        stats.problems.iter().map(|p| tr![
+            td![&p.label],
+            td![p.attempts],
+            td![p.accepted],
+            td![format!("{:.0}%", p.acceptance_rate * 100.0)],
+            td![p.solved],
+            td![optional_time(p.first_solve)],
+            td![optional_time(p.last_solve)],
+            p.solves_per_bucket.iter().map(|n| td![n]),
+        ]),
+    ]
+}
+
+fn view_solved(stats : &stats::ContestStats) -> Node<Msg> {
+    table![
+        C!["statstable"],
+        tr![th!["Resolvidos"], th!["Times"]],
+        stats.teams_by_solved.iter().enumerate().map(|(solved, teams)| tr![td![solved], td![teams]]),
+    ]
+}
+
+fn view(model: &Model) -> Node<Msg> {
+    match &model.stats {
+        None => div![C!["statspanel"], "Carregando..."],
+        Some(stats) => div![
+            C!["statspanel"],
+            div![C!["nomesede"], model.sede.clone().unwrap_or("Geral".to_string()), " - ", stats.teams, " times"],
+            view_problems(stats),
+            view_solved(stats),
+        ],
+    }
+}
+
+pub fn start(e : impl GetElement) {
+    App::start(e, init, update, view);
+}
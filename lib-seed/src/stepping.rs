@@ -1,4 +1,4 @@
-use maratona_animeitor_rust::data;
+use maratona_animeitor_rust::{data, events};
 use seed::{prelude::*, *};
 use crate::views;
 use crate::requests::*;
@@ -10,25 +10,33 @@ extern crate rand;
 fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
     // orders.skip().perform_cmd( fetch_all() );
     orders.send_msg(Msg::Reset);
+    orders.skip().send_msg(Msg::Connect);
     Model { 
         url_filter : get_url_filter(&url),
         source : get_source(&url),
+        secret : get_optional_secret(&url),
         contest: data::ContestFile::dummy(),
         runs: data::RunsFile::empty(),
         current_run: 0,
+        time_slider: None,
         center: None,
         lock_frozen : true,
+        updates : None,
     }
 }
 
 struct Model {
     url_filter : Option<Vec<String>>,
     source : Option<String>,
+    secret : Option<String>,
     contest : data::ContestFile,
     runs: data::RunsFile,
     current_run: usize,
+    /// Time shown by the slider, when the board came from the server.
+    time_slider: Option<data::Minutes>,
     center : Option<String>,
     lock_frozen : bool,
+    updates : Option<WebSocket>,
 }
 
 enum Msg {
@@ -38,14 +46,28 @@ enum Msg {
     Wait,
     Recalculate,
     ToggleFrozen,
-    // FetchedRuns(fetch::Result<data::RunsFile>),
+    Connect,
+    Disconnected,
+    Pushed(events::Update),
+    FetchedRuns(fetch::Result<data::RunsFile>),
     // FetchedContest(fetch::Result<data::ContestFile>),
     Reset,
+    TimeChanged(String),
+    FetchedAt(data::Minutes, fetch::Result<Scoreboard>),
     Fetched(
         fetch::Result<data::RunsFile>,
         fetch::Result<data::ContestFile>),
 }
 
+async fn fetch_at(source : Option<String>, at : data::Minutes, secret : Option<String>) -> Msg {
+    let s = fetch_score_at(&source, at, &secret).await;
+    Msg::FetchedAt(at, s)
+}
+
+async fn fetch_runs(source : Option<String>) -> Msg {
+    Msg::FetchedRuns(fetch_allruns(&source).await)
+}
+
 async fn fetch_all(source : Option<String>) -> Msg {
     let r = fetch_allruns(&source).await;
     let c = fetch_contest(&source).await;
@@ -126,8 +148,28 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::Recenter => {
             model.center = None;
         },
+        Msg::TimeChanged(value) => {
+            if let Ok(at) = value.parse() {
+                model.time_slider = Some(at);
+                orders.skip().perform_cmd( fetch_at(model.source.clone(), at, model.secret.clone()) );
+            }
+        },
+        Msg::FetchedAt(at, Ok((score_board, teams, problems, first_solved))) => {
+            if model.time_slider == Some(at) {
+                model.center = None;
+                model.contest.score_board = score_board;
+                model.contest.teams = teams;
+                model.contest.problems = problems;
+                model.contest.first_solved = first_solved;
+                model.current_run = model.runs.as_vec().iter().take_while(|r| r.time < at).count();
+            }
+        },
+        Msg::FetchedAt(_, Err(e)) => {
+            log!("fetched score error!", e)
+        },
         Msg::Fetched(Ok(runs), Ok(contest)) => {
             model.current_run = 0;
+            model.time_slider = None;
             model.center = None;
             model.runs = runs;
             model.contest = contest;
@@ -139,6 +181,29 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::Fetched(_, Err(e)) => {
             log!("fetched contest error!", e)
         },
+        Msg::Connect => {
+            model.updates = subscribe_updates(&model.source, orders, Msg::Pushed, || Msg::Disconnected);
+            if model.updates.is_none() {
+                orders.send_msg(Msg::Disconnected);
+            }
+        },
+        Msg::Disconnected => {
+            model.updates = None;
+            orders.perform_cmd(cmds::timeout(5_000, || Msg::Connect));
+        },
+        Msg::Pushed(update) => {
+            // keeps the runs already stepped, only the runs still ahead change
+            if !update.events.is_empty() {
+                orders.skip().perform_cmd( fetch_runs(model.source.clone()) );
+            }
+        },
+        Msg::FetchedRuns(Ok(runs)) => {
+            model.runs = runs;
+            model.current_run = model.current_run.min(model.runs.len());
+        },
+        Msg::FetchedRuns(Err(e)) => {
+            log!("fetched runs error!", e)
+        },
         Msg::Reset => {
             orders.skip().perform_cmd( fetch_all(model.source.clone()) );    
         }
@@ -147,6 +212,12 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
 
 fn view(model: &Model) -> Node<Msg> {
     let frozen = if model.lock_frozen {"Frozen Locked"} else { "Frozen Unlocked"};
+    // the server only shows the board after the freeze with the secret
+    let slider_max = if model.secret.is_some() && !model.lock_frozen {
+        model.contest.maximum_time.0
+    } else {
+        model.contest.score_freeze_time.0
+    };
     div![
         div![
             C!["commandpanel"],
@@ -157,10 +228,23 @@ fn view(model: &Model) -> Node<Msg> {
             button!["Reset", ev(Ev::Click, |_| Msg::Reset),],
             button![frozen, ev(Ev::Click, |_| Msg::ToggleFrozen),],
             div!["Runs: ", model.current_run, "/", model.runs.len()],
+            input![
+                C!["timeslider"],
+                attrs!{
+                    At::Type => "range",
+                    At::Min => 0,
+                    At::Max => slider_max,
+                    At::Step => 1,
+                    At::Value => model.time_slider.map(|t| t.0).unwrap_or(0),
+                },
+                input_ev(Ev::Change, Msg::TimeChanged),
+            ],
+            div!["Tempo: ", model.time_slider.map(|t| t.0.to_string()).unwrap_or("-".to_string())],
         ],
         div![
             style!{St::Position => "relative", St::Top => px(60) },
-            views::view_scoreboard(&model.contest, &model.center, &model.url_filter),
+            // the board may be from any time of the contest, so its medals are computed here
+            views::view_scoreboard(&model.contest, &data::Awards::new(&model.contest), &model.center, &model.url_filter),
         ]
     ]
 }
@@ -1,16 +1,19 @@
-use maratona_animeitor_rust::data;
+use maratona_animeitor_rust::{data, events};
 use seed::{prelude::*, *};
 use crate::views;
 use crate::requests::*;
 use crate::helpers::*;
 
 fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
-    orders.stream(streams::interval(1000, || Msg::Reset));
+    orders.send_msg(Msg::Connect);
+    orders.stream(streams::interval(1000, || Msg::Tick));
     Model { 
         source : get_source(&url),
-        p_timer_data: data::TimerData::new(0, 1),
-        timer_data: data::TimerData::new(86399, 86399+1),
+        p_timer_data: data::TimerData::new(data::Seconds(0), data::Minutes(1), data::Minutes(1)),
+        // stands still until the server sends the time
+        timer_data: data::TimerData::new(data::Seconds(86399), data::Minutes(24 * 60), data::Minutes(0)),
         // timer_data: 0,
+        updates: None,
     }
 }
 
@@ -18,9 +21,14 @@ struct Model {
     source : Option<String>,
     p_timer_data: data::TimerData,
     timer_data: data::TimerData,
+    updates: Option<WebSocket>,
 }
 
 enum Msg {
+    Connect,
+    Disconnected,
+    Pushed(events::Update),
+    Tick,
     Reset,
     Fetched(fetch::Result<data::TimerData>),
 }
@@ -40,6 +48,26 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::Fetched(Err(e)) => {
             log!("fetched runs error!", e)
         },
+        Msg::Connect => {
+            model.updates = subscribe_updates(&model.source, orders, Msg::Pushed, || Msg::Disconnected);
+            if model.updates.is_none() {
+                orders.send_msg(Msg::Disconnected);
+            }
+            orders.send_msg(Msg::Reset);
+        },
+        Msg::Disconnected => {
+            model.updates = None;
+            orders.perform_cmd(cmds::timeout(5_000, || Msg::Connect));
+        },
+        Msg::Pushed(update) => {
+            model.p_timer_data = model.timer_data;
+            model.timer_data = update.timer;
+        },
+        // the server only sends the time when it refreshes, so the clock runs here in between
+        Msg::Tick => {
+            model.p_timer_data = model.timer_data;
+            model.timer_data = model.timer_data.advance(data::Seconds(1));
+        },
         Msg::Reset => {
             orders.skip().perform_cmd( fetch_all(model.source.clone()) );    
         }
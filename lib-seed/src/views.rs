@@ -1,23 +1,19 @@
 
-use maratona_animeitor_rust::data::{ContestFile, Team, TimerData};
+use maratona_animeitor_rust::data::{Awards, ContestFile, ProblemInfo, Team, TimerData, Verdict};
 use seed::{prelude::*, *};
 
-pub fn get_color(n : usize) -> String {
-    (if n == 0 {
-        "vermelho"
-    }
-    else if n <= 3 {
-        "ouro"
-    }
-    else if n <= 6 {
-        "prata"
-    }
-    else if n <= 10 {
-        "bronze"
+pub fn medal_class(awards : &Awards, team_login : &str) -> &'static str {
+    awards.medal(team_login).map(|m| m.css_class()).unwrap_or("semcor")
+}
+
+/// The placement of a team, or a dash for unofficial teams.
+pub fn placement_cell<T>(placement : usize, class : &str) -> Node<T> {
+    if placement == 0 {
+        div![C!["cell", "colocacao", "semcor"], "-"]
     }
     else {
-        "semcor"
-    }).to_string()
+        div![C!["cell", "colocacao", class], placement]
+    }
 }
 
 pub fn cell_top(i : usize, center: &Option<usize>) -> String {
@@ -64,11 +60,19 @@ where I : Iterator<Item= &'a usize>
     let mut ret = BTreeMap::new();
 
     for (i, e) in v.iter().enumerate() {
-        *ret.entry(**e).or_default() = i;
+        ret.entry(**e).or_insert(i);
     }
     ret
 }
 
+fn display_rows<'a>(contest: &'a ContestFile, url_filter: &Option<Vec<String>>) -> BTreeMap<&'a String, usize> {
+    contest.score_board.iter()
+        .filter( |login| check_filter_login(url_filter, login))
+        .enumerate()
+        .map( |(i, login)| (login, i + 1))
+        .collect()
+}
+
 fn center_class(p: usize, center : &Option<usize>) -> std::option::Option<seed::Attrs> {
     match center {
         None => None,
@@ -80,19 +84,27 @@ fn number_submissions(s : usize) -> Option<usize> {
     if s == 1 { None } else { Some(s - 1) }
 }
 
-pub fn view_scoreboard<T>(contest: &ContestFile, center: &Option<String>, url_filter: &Option<Vec<String>>) -> Node<T> {
+/// Shows the medals of `awards`, as served by the server, so they follow the configured rules.
+/// The first to solve each problem is the global one, or the first among the filtered teams.
+pub fn view_scoreboard<T>(contest: &ContestFile, awards: &Awards, center: &Option<String>, url_filter: &Option<Vec<String>>) -> Node<T> {
 
-    let p_center = center.as_ref().map(|s| contest.teams[s].placement);
+    let rows = display_rows(contest, url_filter);
+    let p_center = center.as_ref().and_then(|s| rows.get(s).cloned());
 
-    let problem_letters = 
-        vec!["A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z"];
-    let n = contest.number_problems;
-    let all_problems = &problem_letters[..n];
+    let all_problems : Vec<&ProblemInfo> = contest.visible_problems().collect();
     let compressed = compress_placement(contest.teams.values()
-                        .filter( |t| check_filter(url_filter, t))
+                        .filter( |t| check_filter(url_filter, t) && t.official)
                         .map(|t| &t.placement));
 
-    let is_compressed = compressed.len() < contest.teams.len();
+    let first_solved: BTreeMap<String, String> = match url_filter {
+        None => awards.first_to_solve.clone(),
+        Some(_) => contest.first_solves(|t| check_filter(url_filter, t))
+            .into_iter()
+            .map(|(label, first)| (label, first.team_login))
+            .collect(),
+    };
+
+    let is_compressed = rows.len() < contest.teams.len();
     div![
         C!["runstable"],
         div![
@@ -105,27 +117,39 @@ pub fn view_scoreboard<T>(contest: &ContestFile, center: &Option<String>, url_fi
                 // St::Transition => "top 1s ease 0s",
             },
             div![C!["cell", "titulo", if is_compressed {"duplaColocacao"} else {"unicaColocacao"}], "Placar"],
-            all_problems.iter().map( |p| div![C!["cell", "problema"], p])
+            all_problems.iter().map( |p| div![
+                C!["cell", "problema"],
+                attrs!{At::Title => p.name},
+                p.color.as_ref().map( |c| style!{St::BorderColor => c}),
+                &p.label,
+            ])
         ],
         contest.teams.values().filter( |t| check_filter(url_filter, t))
                 .map (|team| {
-            let score = team.score();
-            let p2 = compressed.get(&team.placement).unwrap_or(&0);
+            let score = team.score(&contest.scoring_rules);
+            let local_placement = if team.official {
+                compressed.get(&team.placement).unwrap_or(&0) + 1
+            } else {
+                0
+            };
+            let row = rows.get(&team.login).cloned().unwrap_or(0);
+            let medal = medal_class(awards, &team.login);
             div![
                 id![&team.login],
                 C!["run"],
-                center_class(team.placement, &p_center),
+                center_class(row, &p_center),
                 style!{
                     // St::Top => px(margin_top + (team.placement as i64) * 90),
-                    St::Top => cell_top(*p2+1, &p_center),
+                    St::Top => cell_top(row, &p_center),
                     // St::Top => cell_top(team.placement, &p_center),
                     // St::Position => "absolute",
                     // St::Transition => "top 1s ease 0s",
                 },
-                IF!(is_compressed => div![C!["cell", "colocacao", get_color(team.placement)], team.placement]),
-                div![C!["cell", "colocacao", get_color(*p2+1)], *p2+1],
+                IF!(is_compressed => placement_cell(team.placement, medal)),
+                placement_cell(local_placement, medal),
                 div![
                     C!["cell", "time"],
+                    team.logo_path().map( |src| img![C!["logoimg"], attrs!{At::Src => src}]),
                     div![C!["nomeEscola"], &team.escola],
                     div![C!["nomeTime"], &team.name],
                 ],
@@ -135,22 +159,31 @@ pub fn view_scoreboard<T>(contest: &ContestFile, center: &Option<String>, url_fi
                     div![C!["baixo"], score.penalty],
                 ],
                 all_problems.iter().map( |prob| {
-                    match team.problems.get(*prob) {
+                    match team.problems.get(&prob.label) {
                         None => div![C!["cell", "problema"], "-"],
                         Some(prob_v) => {
                             if prob_v.solved {
+                                let first = first_solved.get(&prob.label)
+                                    .map(|login| login == &team.login)
+                                    .unwrap_or(false);
                                 div![
                                     C!["cell", "problema", "verde"],
+                                    IF!(first => C!["primeiro"]),
                                     div![C!["cima"], "+", number_submissions(prob_v.submissions)],
                                     div![C!["baixo"], prob_v.time_solved],
                                 ]
                             }
                             else {
                                 let color = if prob_v.wait() {"amarelo"} else {"vermelho"};
+                                let verdict = match prob_v.last_verdict {
+                                    _ if prob_v.wait() => "?",
+                                    Some(v) if v != Verdict::Rejected => v.abbreviation(),
+                                    _ => "X",
+                                };
                                 div![
                                     C!["cell", "problema", color],
-                                    div![C!["cima"], "X"],
-                                    div![C!["baixo"], "(", prob_v.submissions, ")"],
+                                    div![C!["cima"], verdict],
+                                    div![C!["baixo"], "(", prob_v.tries(), ")"],
                                 ]
                             }
                         },
@@ -186,8 +219,8 @@ fn changed(a : i64, b: i64) -> &'static str {
 
 pub fn view_clock<T>(time_data: TimerData, ptime_data : TimerData) -> Node<T> {
 
-    let time = time_data.current_time;
-    let ptime = ptime_data.current_time;
+    let time = time_data.current_time.0;
+    let ptime = ptime_data.current_time.0;
 
     let frozen = if time_data.is_frozen() { Some(C!["frozen"]) } else { None };
 
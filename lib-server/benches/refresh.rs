@@ -0,0 +1,96 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use lib_server::dataio::*;
+use maratona_animeitor_rust::data::{Answer, ContestFile, Minutes, RunTuple, RunsFile, Seconds};
+
+/// The 1a fase 2020 with every team and run copied `copies` times.
+fn scaled_contest(copies: usize) -> (ContestFile, Vec<RunTuple>) {
+    let read = |name: &str| std::fs::read_to_string(format!("test/webcast_zip_1a_fase_2020/{}", name)).unwrap();
+    let contest = read_contest(&read("contest")).unwrap();
+    let runs = read_runs(&read("runs")).unwrap();
+
+    let mut scaled = contest.clone();
+    scaled.teams.clear();
+    let mut scaled_runs = Vec::new();
+    for c in 0..copies {
+        for team in contest.teams.values() {
+            let mut team = team.clone();
+            team.login = format!("{}_{}", team.login, c);
+            scaled.teams.insert(team.login.clone(), team);
+        }
+        for r in runs.sorted() {
+            let mut r = r.clone();
+            r.id = r.id * copies as i64 + c as i64;
+            r.team_login = format!("{}_{}", r.team_login, c);
+            scaled_runs.push(r);
+        }
+    }
+    (scaled, scaled_runs)
+}
+
+fn runs_before(runs: &[RunTuple], time: i64) -> RunsFile {
+    RunsFile::new(runs.iter().filter(|r| r.time < Minutes(time)).cloned().collect())
+}
+
+fn refresh(c: &mut Criterion) {
+    for &copies in &[1, 4] {
+        let (contest, runs) = scaled_contest(copies);
+        let all = RunsFile::new(runs.clone());
+
+        c.bench_function(&format!("full rebuild x{}", copies), |b| {
+            b.iter_batched(
+                || (DB::empty(), contest.clone(), all.clone()),
+                |(mut db, contest, runs)| db.refresh_db(Seconds(0), contest, runs).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+
+        c.bench_function(&format!("new minute of runs x{}", copies), |b| {
+            b.iter_batched(
+                || {
+                    let mut db = DB::empty();
+                    db.refresh_db(Seconds(0), contest.clone(), runs_before(&runs, 199)).unwrap();
+                    (db, contest.clone(), runs_before(&runs, 200))
+                },
+                |(mut db, contest, runs)| db.refresh_db(Seconds(0), contest, runs).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+
+        // the runs of the last minutes are pending in the first poll, and judged in the second
+        let mut pending = runs_before(&runs, 200).sorted().clone();
+        for r in pending.iter_mut().filter(|r| r.time >= Minutes(190)) {
+            r.answer = Answer::Wait;
+        }
+        c.bench_function(&format!("pending runs judged x{}", copies), |b| {
+            b.iter_batched(
+                || {
+                    let mut db = DB::empty();
+                    db.refresh_db(Seconds(0), contest.clone(), RunsFile::new(pending.clone())).unwrap();
+                    (db, contest.clone(), runs_before(&runs, 200))
+                },
+                |(mut db, contest, runs)| db.refresh_db(Seconds(0), contest, runs).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+
+        c.bench_function(&format!("unchanged poll x{}", copies), |b| {
+            b.iter_batched(
+                || {
+                    let mut db = DB::empty();
+                    db.refresh_db(Seconds(0), contest.clone(), all.clone()).unwrap();
+                    (db, contest.clone(), all.clone())
+                },
+                |(mut db, contest, runs)| db.refresh_db(Seconds(0), contest, runs).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = refresh
+}
+criterion_main!(benches);
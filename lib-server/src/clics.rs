@@ -0,0 +1,971 @@
+//! The public data of the contest in the shape of the ICPC Contest API (CLICS),
+//! so tools such as the resolver and CDS overlays can read it,
+//! and the reader of the event feeds of other systems, such as DOMjudge.
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, Format, OffsetDateTime};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use maratona_animeitor_rust::data::{
+    self, Answer, ContestFile, Minutes, ProblemInfo, RunTuple, RunsFile, ScoringRules, Seconds, TimeFile, Verdict,
+};
+use maratona_animeitor_rust::events::{ContestEvent, ContestState, EventRecord};
+
+use crate::dataio::ContestIOError;
+
+/// Formats a contest time as `h:mm:ss.sss`.
+pub fn reltime(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let s = seconds.abs();
+    format!("{}{}:{:02}:{:02}.000", sign, s / 3600, s / 60 % 60, s % 60)
+}
+
+/// Parses a contest time in `h:mm:ss.sss`, truncating it to seconds.
+pub fn parse_reltime(s: &str) -> Option<i64> {
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(s) => (-1, s),
+        None => (1, s),
+    };
+    let mut parts = s.split(':');
+    let h: i64 = parts.next()?.parse().ok()?;
+    let m: i64 = parts.next()?.parse().ok()?;
+    let secs = parts.next()?.split('.').next()?;
+    let secs: i64 = secs.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(sign * (h * 3600 + m * 60 + secs))
+}
+
+fn json<T: Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap()
+}
+
+/// The rejection verdicts, in the order their judgement types are served.
+const VERDICTS: [Verdict; 7] = [
+    Verdict::WrongAnswer,
+    Verdict::TimeLimitExceeded,
+    Verdict::RuntimeError,
+    Verdict::MemoryLimitExceeded,
+    Verdict::PresentationError,
+    Verdict::CompilationError,
+    Verdict::Rejected,
+];
+
+/// Id of the judgement type of a verdict. A run rejected for an unknown reason is `RE`, as in the API.
+fn judgement_type_id(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Rejected => "RE",
+        v => v.abbreviation(),
+    }
+}
+
+fn judgement_type_name(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Rejected => "Rejected",
+        Verdict::WrongAnswer => "Wrong Answer",
+        Verdict::TimeLimitExceeded => "Time Limit Exceeded",
+        Verdict::RuntimeError => "Run-Time Error",
+        Verdict::CompilationError => "Compiler Error",
+        Verdict::MemoryLimitExceeded => "Memory Limit Exceeded",
+        Verdict::PresentationError => "Presentation Error",
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Contest {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub formal_name: Option<String>,
+    pub start_time: Option<String>,
+    pub duration: String,
+    /// Missing when the scoreboard is never frozen.
+    pub scoreboard_freeze_duration: Option<String>,
+    #[serde(default)]
+    pub penalty_time: Option<i64>,
+}
+
+/// When each phase of the contest started, if it did.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct State {
+    pub started: Option<String>,
+    pub frozen: Option<String>,
+    pub ended: Option<String>,
+    pub thawed: Option<String>,
+    pub finalized: Option<String>,
+    pub end_of_updates: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Problem {
+    pub id: String,
+    pub label: String,
+    pub name: String,
+    #[serde(default)]
+    pub ordinal: usize,
+    /// Name of the balloon color.
+    pub color: Option<String>,
+    /// Balloon color as `#rrggbb`.
+    pub rgb: Option<String>,
+    #[serde(default)]
+    pub test_data_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Team {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<String>,
+    #[serde(default)]
+    pub group_ids: Vec<String>,
+    /// Hidden teams are not in the scoreboard.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub formal_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JudgementType {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub penalty: bool,
+    pub solved: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Submission {
+    pub id: String,
+    pub language_id: String,
+    pub problem_id: String,
+    pub team_id: String,
+    pub time: String,
+    pub contest_time: String,
+}
+
+/// The judgement of a submission; the type is missing while it is pending.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Judgement {
+    pub id: String,
+    pub submission_id: String,
+    pub judgement_type_id: Option<String>,
+    #[serde(default)]
+    pub start_time: String,
+    #[serde(default)]
+    pub start_contest_time: String,
+    pub end_time: Option<String>,
+    pub end_contest_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RowScore {
+    pub num_solved: usize,
+    pub total_time: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProblemScore {
+    pub problem_id: String,
+    pub num_judged: usize,
+    pub num_pending: usize,
+    pub solved: bool,
+    /// Contest minute of the accepted run.
+    pub time: Option<i64>,
+    #[serde(default)]
+    pub first_to_solve: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScoreboardRow {
+    pub rank: usize,
+    pub team_id: String,
+    pub score: RowScore,
+    pub problems: Vec<ProblemScore>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Scoreboard {
+    pub time: String,
+    pub contest_time: String,
+    pub state: State,
+    pub rows: Vec<ScoreboardRow>,
+}
+
+/// A line of the event feed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Event {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub op: String,
+    pub data: serde_json::Value,
+}
+
+/// Builds the objects of the API from a contest scored with its public runs,
+/// so nothing hidden by the freeze is served.
+pub struct ContestApi<'a> {
+    pub id: &'a str,
+    pub contest: &'a ContestFile,
+    pub runs: &'a RunsFile,
+    pub time: TimeFile,
+    /// Wall-clock time of the start of the contest.
+    pub start: OffsetDateTime,
+}
+
+impl<'a> ContestApi<'a> {
+    fn abstime(&self, seconds: i64) -> String {
+        (self.start + Duration::seconds(seconds)).format(Format::Rfc3339)
+    }
+
+    fn contest_of(&self, state: &ContestState) -> Contest {
+        Contest {
+            id: self.id.to_string(),
+            name: state.contest_name.clone(),
+            formal_name: Some(state.contest_name.clone()),
+            start_time: Some(self.abstime(0)),
+            duration: reltime(Seconds::from(state.maximum_time).0),
+            scoreboard_freeze_duration: if state.score_freeze_time < state.maximum_time {
+                Some(reltime(Seconds::from(state.maximum_time - state.score_freeze_time).0))
+            } else {
+                None
+            },
+            penalty_time: Some(self.contest.scoring_rules.penalty_per_wrong_answer),
+        }
+    }
+
+    pub fn contest(&self) -> Contest {
+        self.contest_of(&ContestState::new(self.contest))
+    }
+
+    fn state_at(&self, time: TimeFile, frozen: bool) -> State {
+        let reached = |t: Minutes| {
+            let t = Seconds::from(t);
+            if time >= t {
+                Some(self.abstime(t.0))
+            } else {
+                None
+            }
+        };
+        State {
+            started: reached(Minutes(0)),
+            frozen: if frozen { reached(self.contest.score_freeze_time) } else { None },
+            ended: reached(self.contest.maximum_time),
+            ..State::default()
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state_at(self.time, self.contest.is_frozen())
+    }
+
+    fn problem(&self, ordinal: usize, info: &ProblemInfo) -> Problem {
+        let color = info.color.clone().filter(|c| !c.starts_with('#'));
+        let rgb = info.color.clone().filter(|c| c.starts_with('#'));
+        Problem {
+            id: info.label.clone(),
+            label: info.label.clone(),
+            name: if info.name.is_empty() { info.label.clone() } else { info.name.clone() },
+            ordinal,
+            color,
+            rgb,
+            test_data_count: 0,
+        }
+    }
+
+    pub fn problems(&self) -> Vec<Problem> {
+        self.contest
+            .visible_problems()
+            .enumerate()
+            .map(|(i, info)| self.problem(i, info))
+            .collect()
+    }
+
+    fn team(&self, team: &data::Team) -> Team {
+        Team {
+            id: team.login.clone(),
+            name: team.name.clone(),
+            display_name: None,
+            organization_id: None,
+            group_ids: team.categories.clone(),
+            hidden: false,
+        }
+    }
+
+    pub fn teams(&self) -> Vec<Team> {
+        self.contest.teams.values().map(|t| self.team(t)).collect()
+    }
+
+    /// Every judgement type a judgement may have, with `AC` first.
+    pub fn judgement_types(&self) -> Vec<JudgementType> {
+        let accepted = JudgementType {
+            id: "AC".to_string(),
+            name: "Accepted".to_string(),
+            penalty: false,
+            solved: true,
+        };
+        let rejected = VERDICTS.iter().map(|&v| JudgementType {
+            id: judgement_type_id(v).to_string(),
+            name: judgement_type_name(v).to_string(),
+            penalty: v.penalized(),
+            solved: false,
+        });
+        std::iter::once(accepted).chain(rejected).collect()
+    }
+
+    /// The language is not known from the webcast.
+    pub fn submission(&self, r: &RunTuple) -> Submission {
+        let time = Seconds::from(r.time).0;
+        Submission {
+            id: r.id.to_string(),
+            language_id: "unknown".to_string(),
+            problem_id: r.prob.clone(),
+            team_id: r.team_login.clone(),
+            time: self.abstime(time),
+            contest_time: reltime(time),
+        }
+    }
+
+    pub fn submissions(&self) -> Vec<Submission> {
+        self.runs.sorted().iter().map(|r| self.submission(r)).collect()
+    }
+
+    /// The judgement of a run, without a type while it is pending.
+    /// The webcast has no judging times, so the judgement ends when the run is submitted.
+    pub fn judgement(&self, r: &RunTuple) -> Option<Judgement> {
+        let judgement_type_id = match &r.answer {
+            Answer::Yes(_) => Some("AC".to_string()),
+            Answer::No(verdict) => Some(judgement_type_id(*verdict).to_string()),
+            Answer::Wait => None,
+            Answer::Unk => return None,
+        };
+        let time = Seconds::from(r.time).0;
+        let judged = judgement_type_id.is_some();
+        Some(Judgement {
+            id: r.id.to_string(),
+            submission_id: r.id.to_string(),
+            judgement_type_id,
+            start_time: self.abstime(time),
+            start_contest_time: reltime(time),
+            end_time: if judged { Some(self.abstime(time)) } else { None },
+            end_contest_time: if judged { Some(reltime(time)) } else { None },
+        })
+    }
+
+    pub fn judgements(&self) -> Vec<Judgement> {
+        self.runs.sorted().iter().filter_map(|r| self.judgement(r)).collect()
+    }
+
+    /// Official teams in the order of the scoreboard.
+    /// Runs count as judged up to the accepted one, without compilation errors and pending runs.
+    pub fn scoreboard(&self) -> Scoreboard {
+        let contest = self.contest;
+        let mut judged: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        let mut solved: BTreeSet<(&str, &str)> = BTreeSet::new();
+        for r in self.runs.sorted() {
+            let key = (r.team_login.as_str(), r.prob.as_str());
+            if solved.contains(&key) {
+                continue;
+            }
+            let counts = match &r.answer {
+                Answer::Yes(_) => {
+                    solved.insert(key);
+                    true
+                },
+                Answer::No(verdict) => verdict.penalized(),
+                Answer::Wait | Answer::Unk => false,
+            };
+            if counts {
+                *judged.entry(key).or_insert(0) += 1;
+            }
+        }
+        let rows = contest
+            .score_board
+            .iter()
+            .filter_map(|login| contest.teams.get(login))
+            .filter(|t| t.official)
+            .map(|t| {
+                let score = t.score(&contest.scoring_rules);
+                let problems = contest
+                    .visible_problems()
+                    .filter_map(|info| {
+                        let p = t.problems.get(&info.label)?;
+                        Some(ProblemScore {
+                            problem_id: info.label.clone(),
+                            num_judged: judged.get(&(t.login.as_str(), info.label.as_str())).cloned().unwrap_or(0),
+                            num_pending: p.answers.len(),
+                            solved: p.solved,
+                            time: if p.solved { Some(p.time_solved) } else { None },
+                            first_to_solve: contest
+                                .first_solved
+                                .get(&info.label)
+                                .map(|f| f.team_login == t.login)
+                                .unwrap_or(false),
+                        })
+                    })
+                    .collect();
+                ScoreboardRow {
+                    rank: t.placement,
+                    team_id: t.login.clone(),
+                    score: RowScore {
+                        num_solved: score.solved,
+                        total_time: score.penalty,
+                    },
+                    problems,
+                }
+            })
+            .collect();
+
+        Scoreboard {
+            time: self.abstime(self.time.0),
+            contest_time: reltime(self.time.0),
+            state: self.state(),
+            rows,
+        }
+    }
+
+    /// The events of the feed for a record of the log.
+    /// A new run comes with its judgement, numbered after the record.
+    pub fn events(&self, record: &EventRecord) -> Vec<Event> {
+        let event = |id: String, kind: &str, op: &str, data: serde_json::Value| Event {
+            id,
+            kind: kind.to_string(),
+            op: op.to_string(),
+            data,
+        };
+        let id = record.id.to_string();
+        match &record.event {
+            ContestEvent::Contest(state) => vec![event(id, "contests", "update", json(&self.contest_of(state)))],
+            ContestEvent::Problem(info) => match self.contest.visible_problems().position(|p| p.label == info.label) {
+                None => Vec::new(),
+                Some(ordinal) => vec![event(id, "problems", "update", json(&self.problem(ordinal, info)))],
+            },
+            ContestEvent::Team(team) => vec![event(id, "teams", "update", json(&self.team(team)))],
+            ContestEvent::TeamRemoved(login) => {
+                vec![event(id, "teams", "delete", serde_json::json!({ "id": login }))]
+            },
+            ContestEvent::Frozen => vec![event(id, "state", "update", json(&self.state_at(record.time, true)))],
+            ContestEvent::Unfrozen => vec![event(id, "state", "update", json(&self.state_at(record.time, false)))],
+            ContestEvent::NewRun(r) => {
+                let mut events = vec![event(id.clone(), "submissions", "create", json(&self.submission(r)))];
+                if let Some(j) = self.judgement(r) {
+                    events.push(event(format!("{}.1", id), "judgements", "create", json(&j)));
+                }
+                events
+            },
+            ContestEvent::RunJudged(r) | ContestEvent::VerdictChanged { after: r, .. } => self
+                .judgement(r)
+                .map(|j| event(id, "judgements", "update", json(&j)))
+                .into_iter()
+                .collect(),
+            ContestEvent::RunDeleted(r) => {
+                vec![event(id, "submissions", "delete", serde_json::json!({ "id": r.id.to_string() }))]
+            },
+        }
+    }
+
+    /// The event feed as newline-delimited JSON, after the event `since_id` when given,
+    /// or `None` when there is no event `since_id`.
+    /// It starts with the judgement types, numbered `0.1`, `0.2` and so on, before the first record.
+    pub fn event_feed(&self, records: &[EventRecord], since_id: Option<&str>) -> Option<String> {
+        let types = self.judgement_types().into_iter().enumerate().map(|(i, t)| Event {
+            id: format!("0.{}", i + 1),
+            kind: "judgement-types".to_string(),
+            op: "create".to_string(),
+            data: json(&t),
+        });
+        let events: Vec<Event> = types.chain(records.iter().flat_map(|r| self.events(r))).collect();
+        let first = match since_id {
+            None => 0,
+            Some(since) => events.iter().position(|e| e.id == since)? + 1,
+        };
+        Some(events[first..]
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap() + "\n")
+            .collect())
+    }
+}
+
+/// A line of an event feed. Older versions of the API say `op: delete`,
+/// newer ones send the id of the object without data.
+#[derive(Deserialize)]
+struct FeedLine {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    op: Option<String>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// The objects of an event feed, as they are after the last event.
+#[derive(Debug, Clone, Default)]
+pub struct FeedReader {
+    pub contest: Option<Contest>,
+    pub state: State,
+    pub judgement_types: BTreeMap<String, JudgementType>,
+    pub problems: BTreeMap<String, Problem>,
+    pub organizations: BTreeMap<String, Organization>,
+    pub teams: BTreeMap<String, Team>,
+    pub submissions: BTreeMap<String, Submission>,
+    /// Submissions in the order they were first seen.
+    order: Vec<String>,
+    /// Number of the run of each submission.
+    run_ids: BTreeMap<String, i64>,
+    used_ids: BTreeSet<i64>,
+    /// The last judgement of each submission.
+    judgements: BTreeMap<String, Judgement>,
+}
+
+fn object_id(data: &serde_json::Value) -> Option<String> {
+    data.get("id").and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+fn upsert<T: serde::de::DeserializeOwned>(
+    map: &mut BTreeMap<String, T>,
+    id: Option<String>,
+    data: serde_json::Value,
+) -> Result<(), ContestIOError> {
+    match id {
+        None => (),
+        Some(id) if data.is_null() => {
+            map.remove(&id);
+        },
+        Some(id) => {
+            map.insert(id, serde_json::from_value(data)?);
+        },
+    }
+    Ok(())
+}
+
+impl FeedReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the events of a newline-delimited JSON feed, skipping empty lines.
+    pub fn read(&mut self, feed: &str) -> Result<(), ContestIOError> {
+        for line in feed.lines().filter(|l| !l.trim().is_empty()) {
+            let line: FeedLine = serde_json::from_str(line)?;
+            match line.data {
+                // a whole collection at once
+                serde_json::Value::Array(items) => {
+                    for item in items {
+                        self.apply(&line.kind, None, item)?;
+                    }
+                },
+                data => {
+                    let id = object_id(&data).or(line.id);
+                    let data = if line.op.as_deref() == Some("delete") { serde_json::Value::Null } else { data };
+                    self.apply(&line.kind, id, data)?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, kind: &str, id: Option<String>, data: serde_json::Value) -> Result<(), ContestIOError> {
+        let id = object_id(&data).or(id);
+        match kind {
+            "contests" | "contest" if !data.is_null() => self.contest = Some(serde_json::from_value(data)?),
+            "state" if !data.is_null() => self.state = serde_json::from_value(data)?,
+            "judgement-types" => upsert(&mut self.judgement_types, id, data)?,
+            "problems" => upsert(&mut self.problems, id, data)?,
+            "organizations" => upsert(&mut self.organizations, id, data)?,
+            "teams" => upsert(&mut self.teams, id, data)?,
+            "submissions" => {
+                if let Some(id) = &id {
+                    self.number(id);
+                }
+                upsert(&mut self.submissions, id, data)?
+            },
+            "judgements" => {
+                let judgement: Option<Judgement> = if data.is_null() { None } else { Some(serde_json::from_value(data)?) };
+                match judgement {
+                    Some(j) => {
+                        self.judgements.insert(j.submission_id.clone(), j);
+                    },
+                    None => self.judgements.retain(|_, j| Some(&j.id) != id.as_ref()),
+                }
+            },
+            // awards, clarifications, languages and the rest are not used
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Numbers a submission the first time it is seen. An id that is not a number, or whose number
+    /// was already given, gets the next number after the largest so far. The numbers depend only
+    /// on the feed up to the submission, so they are the same on every read of a growing feed.
+    fn number(&mut self, id: &str) {
+        if self.run_ids.contains_key(id) {
+            return;
+        }
+        let number = match id.parse::<i64>() {
+            Ok(n) if !self.used_ids.contains(&n) => n,
+            _ => self.used_ids.iter().next_back().map(|n| n + 1).unwrap_or(1),
+        };
+        self.used_ids.insert(number);
+        self.run_ids.insert(id.to_string(), number);
+        self.order.push(id.to_string());
+    }
+
+    fn answer(&self, judgement: Option<&Judgement>, time: Minutes) -> Answer {
+        let type_id = match judgement.and_then(|j| j.judgement_type_id.as_ref()) {
+            None => return Answer::Wait,
+            Some(t) => t,
+        };
+        let known = self.judgement_types.get(type_id);
+        if known.map(|t| t.solved).unwrap_or(type_id == "AC") {
+            return Answer::Yes(time.0);
+        }
+        match Verdict::from_abbreviation(type_id) {
+            Some(verdict) => Answer::No(verdict),
+            None if known.map(|t| !t.penalty).unwrap_or(false) => Answer::No(Verdict::CompilationError),
+            None => Answer::No(Verdict::Rejected),
+        }
+    }
+
+    /// The contest as of the last event, and its time: the end of the contest once it ended,
+    /// otherwise, for a live feed, the time since the start at `now`, or else the time
+    /// of the latest submission or judgement. The time is kept inside the contest.
+    pub fn snapshot(&self, now: Option<OffsetDateTime>) -> Result<(TimeFile, ContestFile, RunsFile), ContestIOError> {
+        let contest = self
+            .contest
+            .as_ref()
+            .ok_or_else(|| ContestIOError::Info("event feed without a contest".to_string()))?;
+        let invalid = |s: &str| ContestIOError::Info(format!("invalid contest time: {}", s));
+        let maximum_time = Minutes(parse_reltime(&contest.duration).ok_or_else(|| invalid(&contest.duration))? / 60);
+        let score_freeze_time = match (&contest.scoreboard_freeze_duration, &self.state.thawed) {
+            (Some(d), None) => maximum_time - Minutes(parse_reltime(d).ok_or_else(|| invalid(d))? / 60),
+            _ => maximum_time,
+        };
+
+        let mut problems: Vec<&Problem> = self.problems.values().collect();
+        problems.sort_by_key(|p| p.ordinal);
+        let labels: BTreeMap<&String, &String> = problems.iter().map(|p| (&p.id, &p.label)).collect();
+        let problems = problems
+            .iter()
+            .map(|p| ProblemInfo {
+                label: p.label.clone(),
+                name: p.name.clone(),
+                color: p.rgb.clone().or_else(|| p.color.clone()),
+                hidden: false,
+            })
+            .collect();
+
+        let teams = self
+            .teams
+            .values()
+            .filter(|t| !t.hidden)
+            .map(|t| {
+                let organization = t.organization_id.as_ref().and_then(|o| self.organizations.get(o));
+                let escola = organization.map(|o| o.name.as_str()).unwrap_or("");
+                let mut team = data::Team::new(&t.id, escola, t.display_name.as_ref().unwrap_or(&t.name));
+                if let Some(formal_name) = organization.and_then(|o| o.formal_name.clone()) {
+                    team.institution = formal_name;
+                }
+                team.categories = t.group_ids.clone();
+                team
+            })
+            .collect::<Vec<_>>();
+
+        let mut runs = Vec::new();
+        for id in &self.order {
+            let s = match self.submissions.get(id) {
+                None => continue,
+                Some(s) => s,
+            };
+            let prob = match labels.get(&s.problem_id) {
+                None => continue,
+                Some(label) => label.to_string(),
+            };
+            if !teams.iter().any(|t| t.login == s.team_id) {
+                continue;
+            }
+            let time = Minutes(parse_reltime(&s.contest_time).ok_or_else(|| invalid(&s.contest_time))? / 60);
+            runs.push(RunTuple {
+                id: self.run_ids[id],
+                time,
+                team_login: s.team_id.clone(),
+                prob,
+                answer: self.answer(self.judgements.get(id), time),
+            });
+        }
+
+        let judged = self.judgements.values().filter_map(|j| j.end_contest_time.as_ref());
+        let latest = self
+            .submissions
+            .values()
+            .map(|s| &s.contest_time)
+            .chain(judged)
+            .filter_map(|t| parse_reltime(t))
+            .max()
+            .unwrap_or(0);
+        let start = contest.start_time.as_ref().and_then(|s| OffsetDateTime::parse(s, Format::Rfc3339).ok());
+        let time = match (&self.state.ended, &self.state.started, start, now) {
+            (Some(_), _, _, _) => Seconds::from(maximum_time),
+            (None, Some(_), Some(start), Some(now)) => Seconds((now - start).whole_seconds()),
+            _ => Seconds(latest),
+        };
+        let time = time.max(Seconds(0)).min(Seconds::from(maximum_time));
+
+        let contest_file = ContestFile::new(
+            contest.formal_name.clone().unwrap_or_else(|| contest.name.clone()),
+            teams,
+            time.minutes(),
+            maximum_time,
+            score_freeze_time,
+            ScoringRules::new(contest.penalty_time.unwrap_or(20)),
+            problems,
+        );
+        Ok((time, contest_file, RunsFile::new(runs)))
+    }
+}
+
+/// Reads the time, contest and runs from a whole event feed.
+/// `now` is the wall clock of a live feed; a feed read from a file has none.
+pub fn read_event_feed(feed: &str, now: Option<OffsetDateTime>) -> Result<(TimeFile, ContestFile, RunsFile), ContestIOError> {
+    let mut reader = FeedReader::new();
+    reader.read(feed)?;
+    reader.snapshot(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataio::{read_contest, read_runs, DB};
+    use maratona_animeitor_rust::configdata::ContestConfig;
+
+    fn db_1a_fase_2020() -> DB {
+        let read = |name: &str| std::fs::read_to_string(format!("test/webcast_zip_1a_fase_2020/{}", name)).unwrap();
+        let contest = read_contest(&read("contest")).unwrap();
+        let runs = read_runs(&read("runs")).unwrap();
+        let config = ContestConfig {
+            contest_id: Some("1a_fase_2020".to_string()),
+            start_time: Some("2020-09-19T14:00:00+00:00".to_string()),
+            ..ContestConfig::default()
+        };
+        let mut db = DB::with_config(config);
+        db.refresh_db(Seconds(300 * 60), contest, runs).unwrap();
+        db
+    }
+
+    #[test]
+    fn formats_contest_times() {
+        assert_eq!(reltime(0), "0:00:00.000");
+        assert_eq!(reltime(3725), "1:02:05.000");
+        assert_eq!(reltime(-300), "-0:05:00.000");
+    }
+
+    #[test]
+    fn contest_and_state() {
+        let db = db_1a_fase_2020();
+        let api = db.contest_api();
+        let contest = api.contest();
+        assert_eq!(contest.id, "1a_fase_2020");
+        assert_eq!(contest.start_time.as_deref(), Some("2020-09-19T14:00:00+00:00"));
+        assert_eq!(contest.duration, "5:00:00.000");
+        assert_eq!(contest.scoreboard_freeze_duration.as_deref(), Some("1:00:00.000"));
+
+        let state = api.state();
+        assert_eq!(state.started.as_deref(), Some("2020-09-19T14:00:00+00:00"));
+        assert_eq!(state.frozen.as_deref(), Some("2020-09-19T18:00:00+00:00"));
+        assert_eq!(state.ended.as_deref(), Some("2020-09-19T19:00:00+00:00"));
+        assert_eq!(state.thawed, None);
+    }
+
+    #[test]
+    fn frozen_judgements_are_pending() {
+        let db = db_1a_fase_2020();
+        let api = db.contest_api();
+        let freeze = api.contest.score_freeze_time;
+        let judgements = api.judgements();
+        assert_eq!(judgements.len(), db.run_file.len());
+        for (r, j) in db.run_file.sorted().iter().zip(&judgements) {
+            assert_eq!(j.submission_id, r.id.to_string());
+            assert_eq!(j.judgement_type_id.is_none(), r.time >= freeze);
+        }
+
+        let scoreboard = api.scoreboard();
+        let official = api.contest.teams.values().filter(|t| t.official).count();
+        assert_eq!(scoreboard.rows.len(), official);
+        assert!(scoreboard.rows.windows(2).all(|w| w[0].rank <= w[1].rank));
+        assert!(scoreboard.rows.iter().flat_map(|r| &r.problems).any(|p| p.num_pending > 0));
+    }
+
+    #[test]
+    fn judged_runs_stop_at_the_accepted_one() -> Result<(), ContestIOError> {
+        let teams = vec![data::Team::new("teama", "A", "a")];
+        let contest = ContestFile::new(
+            "Teste".to_string(), teams, Minutes(60), Minutes(300), Minutes(240), ScoringRules::default(), ProblemInfo::numbered(1));
+        let runs = read_runs(&String::from(
+            "1\x1c10\x1cteama\x1cA\x1cCE\n2\x1c20\x1cteama\x1cA\x1cWA\n3\x1c30\x1cteama\x1cA\x1cY\n4\x1c40\x1cteama\x1cA\x1cWA"))?;
+        let mut db = DB::empty();
+        db.refresh_db(Seconds(60 * 60), contest, runs)?;
+
+        let scoreboard = db.contest_api().scoreboard();
+        let a = &scoreboard.rows[0].problems[0];
+        assert!(a.solved);
+        assert_eq!((a.num_judged, a.num_pending), (2, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn event_feed_since_id() {
+        let db = db_1a_fase_2020();
+        let api = db.contest_api();
+        let feed = api.event_feed(&db.events, None).unwrap();
+        let events: Vec<Event> = feed.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let types = api.judgement_types();
+        assert_eq!(events[0].kind, "judgement-types");
+        assert_eq!(events[types.len()].kind, "contests");
+        let type_ids: Vec<_> = types.iter().map(|t| t.id.as_str()).collect();
+        assert!(api.judgements().iter().filter_map(|j| j.judgement_type_id.as_deref()).all(|t| type_ids.contains(&t)));
+        let submissions = events.iter().filter(|e| e.kind == "submissions").count();
+        let judgements = events.iter().filter(|e| e.kind == "judgements").count();
+        assert_eq!(submissions, db.run_file.len());
+        assert_eq!(judgements, db.run_file.len());
+
+        let middle = &events[events.len() / 2].id;
+        let rest = api.event_feed(&db.events, Some(middle)).unwrap();
+        assert_eq!(rest.lines().count(), events.len() - events.len() / 2 - 1);
+        assert!(api.event_feed(&db.events, Some(&events.last().unwrap().id)).unwrap().is_empty());
+        assert!(api.event_feed(&db.events, Some("nope")).is_none());
+    }
+
+    #[test]
+    fn judgement_types() {
+        let db = db_1a_fase_2020();
+        let api = db.contest_api();
+        let types = api.judgement_types();
+        let get = |id: &str| types.iter().find(|t| t.id == id).unwrap();
+        assert!(get("AC").solved && !get("AC").penalty);
+        assert!(!get("CE").solved && !get("CE").penalty);
+        assert!(!get("WA").solved && get("WA").penalty);
+        assert!(get("RE").penalty);
+        assert!(types.iter().all(|t| t.id != "NO"));
+
+        let rejected = RunTuple {
+            id: 1,
+            time: Minutes(10),
+            team_login: "teambral001".to_string(),
+            prob: "A".to_string(),
+            answer: Answer::No(Verdict::Rejected),
+        };
+        assert_eq!(api.judgement(&rejected).unwrap().judgement_type_id.as_deref(), Some("RE"));
+    }
+
+    #[test]
+    fn parses_contest_times() {
+        assert_eq!(parse_reltime("0:00:00.000"), Some(0));
+        assert_eq!(parse_reltime("1:02:05.999"), Some(3725));
+        assert_eq!(parse_reltime("-0:05:00"), Some(-300));
+        assert_eq!(parse_reltime("5:00"), None);
+    }
+
+    #[test]
+    fn clamps_the_time_of_a_running_feed() -> Result<(), ContestIOError> {
+        let feed = std::fs::read_to_string("test/clics_event_feed.ndjson")?;
+        let running = feed.lines().take(31).collect::<Vec<_>>().join("\n");
+        let start = OffsetDateTime::parse("2021-03-27T13:00:00+00:00", Format::Rfc3339).unwrap();
+
+        let (time, _, _) = read_event_feed(&running, Some(OffsetDateTime::now_utc()))?;
+        assert_eq!(time, Seconds(300 * 60));
+        let (time, _, _) = read_event_feed(&running, Some(start - time::Duration::hours(1)))?;
+        assert_eq!(time, Seconds(0));
+        let (time, _, _) = read_event_feed(&running, None)?;
+        assert_eq!(time, Seconds(270 * 60));
+        Ok(())
+    }
+
+    #[test]
+    fn reads_event_feed() -> Result<(), ContestIOError> {
+        let feed = std::fs::read_to_string("test/clics_event_feed.ndjson")?;
+        let (time, contest, runs) = read_event_feed(&feed, Some(OffsetDateTime::now_utc()))?;
+
+        assert_eq!(time, Seconds(300 * 60));
+        assert_eq!(contest.contest_name, "Demo Contest");
+        assert_eq!(contest.score_freeze_time, Minutes(240));
+        let colors: Vec<_> = contest.problems.iter().map(|p| (p.label.as_str(), p.color.as_deref())).collect();
+        assert_eq!(colors, vec![("A", Some("#ff0000")), ("B", Some("blue"))]);
+
+        // the hidden team and its runs are left out
+        assert_eq!(contest.teams.len(), 2);
+        assert_eq!(contest.teams["1"].escola, "UFSC");
+        assert_eq!(contest.teams["1"].institution, "Universidade Federal de Santa Catarina");
+        assert_eq!(contest.teams["2"].name, "Beta Team");
+
+        let answers: Vec<_> = runs.sorted().iter().map(|r| (r.id, r.answer.clone())).collect();
+        assert_eq!(
+            answers,
+            vec![
+                (1, Answer::Yes(10)),
+                (2, Answer::No(Verdict::WrongAnswer)),
+                (3, Answer::Yes(35)),
+                (4, Answer::No(Verdict::CompilationError)),
+                (5, Answer::Yes(120)),
+                (7, Answer::Wait),
+            ]
+        );
+
+        let mut db = DB::empty();
+        db.refresh_db(time, contest, runs)?;
+        let (order, teams, _, _) = db.get_scoreboard();
+        assert_eq!(order, &vec!["1".to_string(), "2".to_string()]);
+        assert_eq!(teams["1"].score(&ScoringRules::default()).penalty, 130);
+        assert!(teams["2"].wait());
+        Ok(())
+    }
+
+    #[test]
+    fn numbers_submissions_with_text_ids() -> Result<(), ContestIOError> {
+        let fixture = std::fs::read_to_string("test/clics_event_feed.ndjson")?;
+        let header: String = fixture.lines().take(12).map(|l| format!("{}\n", l)).collect();
+        let submission = |id: &str, minute: i64| {
+            format!(
+                r#"{{"type":"submissions","op":"create","data":{{"id":"{}","language_id":"cpp","problem_id":"hello","team_id":"1","time":"2021-03-27T13:00:00.000+00:00","contest_time":"0:{:02}:00.000"}}}}"#,
+                id, minute
+            ) + "\n"
+        };
+        let ids = |feed: &str| -> Result<Vec<i64>, ContestIOError> {
+            let (_, _, runs) = read_event_feed(feed, Some(OffsetDateTime::now_utc()))?;
+            Ok(runs.sorted().iter().map(|r| r.id).collect())
+        };
+
+        let feed = header + &submission("1", 1) + &submission("a1b", 2) + &submission("2", 3);
+        assert_eq!(ids(&feed)?, vec![1, 2, 3]);
+
+        // a later read of the feed keeps the numbers already given
+        let feed = feed + &submission("x", 4) + &submission("9", 5) + &submission("3", 6);
+        assert_eq!(ids(&feed)?, vec![1, 2, 3, 4, 9, 10]);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_its_own_event_feed() -> Result<(), ContestIOError> {
+        let db = db_1a_fase_2020();
+        let feed = db.contest_api().event_feed(&db.events, None).unwrap();
+        let (time, contest, runs) = read_event_feed(&feed, Some(OffsetDateTime::now_utc()))?;
+        assert_eq!(time, db.time_file);
+        assert_eq!(runs.sorted(), db.run_file.sorted());
+
+        let mut read = DB::empty();
+        read.refresh_db(time, contest, runs)?;
+        assert_eq!(read.get_scoreboard().0, db.get_scoreboard().0);
+        Ok(())
+    }
+}
@@ -1,8 +1,17 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::{self, Read};
 
+use maratona_animeitor_rust::configdata::{ConfigError, ContestConfig, SedeRanking};
+use maratona_animeitor_rust::qualification::{Qualified, QualificationError};
+use maratona_animeitor_rust::stats::ContestStats;
 use maratona_animeitor_rust::data::*;
+use maratona_animeitor_rust::events::{ContestEvent, ContestState, EventRecord, Update};
+
+use tokio::sync::broadcast;
+use time::{Duration, Format, OffsetDateTime};
+
+use crate::clics::ContestApi;
 
 type ContestIOResult<T> = Result<T, ContestIOError>;
 
@@ -15,6 +24,9 @@ pub enum ContestIOError {
     Hyper(hyper::Error),
     ParseInt(std::num::ParseIntError),
     InvalidAnswer(String),
+    Json(serde_json::Error),
+    Config(ConfigError),
+    Qualification(QualificationError),
     Chain(ContestError),
     Info(String),
 }
@@ -49,6 +61,24 @@ impl std::convert::From<maratona_animeitor_rust::data::ContestError> for Contest
     }
 }
 
+impl std::convert::From<serde_json::Error> for ContestIOError {
+    fn from(error: serde_json::Error) -> Self {
+        ContestIOError::Json(error)
+    }
+}
+
+impl std::convert::From<ConfigError> for ContestIOError {
+    fn from(error: ConfigError) -> Self {
+        ContestIOError::Config(error)
+    }
+}
+
+impl std::convert::From<QualificationError> for ContestIOError {
+    fn from(error: QualificationError) -> Self {
+        ContestIOError::Qualification(error)
+    }
+}
+
 impl std::convert::From<std::num::ParseIntError> for ContestIOError {
     fn from(error: std::num::ParseIntError) -> Self {
         ContestIOError::ParseInt(error)
@@ -84,9 +114,11 @@ fn read_to_string(s: &str) -> io::Result<String> {
 fn from_string_answer(t: &str, tim: i64) -> Result<Answer, ContestIOError> {
     match t {
         "Y" => Ok(Answer::Yes(tim)),
-        "N" => Ok(Answer::No),
+        "N" => Ok(Answer::No(Verdict::Rejected)),
         "?" => Ok(Answer::Wait),
-        _ => Err(ContestIOError::InvalidAnswer(t.to_string())),
+        _ => Verdict::from_abbreviation(t)
+            .map(Answer::No)
+            .ok_or_else(|| ContestIOError::InvalidAnswer(t.to_string())),
     }
 }
 
@@ -94,8 +126,8 @@ impl FromString for RunTuple {
     fn from_string(line: &str) -> Result<Self, ContestIOError> {
         let v: Vec<&str> = line.split('').collect();
         let id = v[0].parse()?;
-        let time = v[1].parse()?;
-        let ans = from_string_answer(v[4], time)?;
+        let time: Minutes = v[1].parse()?;
+        let ans = from_string_answer(v[4], time.0)?;
 
         Ok(Self {
             id: id,
@@ -134,8 +166,8 @@ impl FromString for ContestFile {
             current_time,
             maximum_time,
             score_freeze_time,
-            penalty,
-            number_problems,
+            ScoringRules::new(penalty),
+            ProblemInfo::numbered(number_problems),
         ))
     }
 }
@@ -162,6 +194,10 @@ impl FromString for RunsFile {
     }
 }
 
+/// Events kept in the log by default. Older events are dropped,
+/// so a client that falls further behind than this misses them.
+pub const EVENT_LOG_LIMIT: usize = 100_000;
+
 #[derive(Debug)]
 pub struct DB {
     pub run_file: RunsFile,
@@ -169,8 +205,137 @@ pub struct DB {
     pub contest_file_begin: ContestFile,
     contest_file: ContestFile,
     pub time_file: TimeFile,
+    pub config: ContestConfig,
+    pub sede_rankings: BTreeMap<String, SedeRanking>,
+    pub rank_history: RankHistory,
+    scores: ScoreCache,
+    /// Changes seen between consecutive updates, in order, the latest `event_limit` of them.
+    pub events: Vec<EventRecord>,
+    pub event_limit: usize,
+    updates: broadcast::Sender<Update>,
+    /// Wall-clock start of the contest, from the config or estimated at the first update.
+    pub start_time: Option<OffsetDateTime>,
+}
+
+/// Differences between the public data of two snapshots of the contest.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapshotDiff {
+    /// The new settings of the contest, when they changed.
+    pub contest: Option<ContestState>,
+    /// Problems added or changed.
+    pub problems: Vec<ProblemInfo>,
+    /// Teams added or changed.
+    pub teams: Vec<Team>,
+    pub teams_removed: Vec<String>,
+    /// Whether the scoreboard is frozen, when that changed.
+    pub frozen: Option<bool>,
+    /// Runs not seen before, sorted by time.
+    pub new_runs: Vec<RunTuple>,
+    /// Pending runs that got a verdict.
+    pub judged: Vec<RunTuple>,
+    /// Runs that changed after being judged, before and after the change.
+    pub changed: Vec<(RunTuple, RunTuple)>,
+    pub deleted: Vec<RunTuple>,
+}
+
+impl SnapshotDiff {
+    pub fn new(before: &ContestFile, before_runs: &RunsFile, after: &ContestFile, after_runs: &RunsFile) -> Self {
+        let mut diff = SnapshotDiff::default();
+
+        let state = ContestState::new(after);
+        if ContestState::new(before) != state {
+            diff.contest = Some(state);
+        }
+        diff.problems = after.problems.iter().filter(|p| !before.problems.contains(p)).cloned().collect();
+        diff.teams = after.teams.values().filter(|t| before.teams.get(&t.login) != Some(t)).cloned().collect();
+        diff.teams_removed = before.teams.keys().filter(|t| !after.teams.contains_key(*t)).cloned().collect();
+        if before.is_frozen() != after.is_frozen() {
+            diff.frozen = Some(after.is_frozen());
+        }
+
+        let mut previous: BTreeMap<i64, &RunTuple> = before_runs.sorted().iter().map(|r| (r.id, r)).collect();
+        for r in after_runs.sorted() {
+            match previous.remove(&r.id) {
+                None => diff.new_runs.push(r.clone()),
+                Some(p) if p == r => (),
+                Some(p) if p.answer == Answer::Wait
+                    && (p.time, &p.team_login, &p.prob) == (r.time, &r.team_login, &r.prob) => {
+                    diff.judged.push(r.clone());
+                },
+                Some(p) => diff.changed.push((p.clone(), r.clone())),
+            }
+        }
+        diff.deleted = previous.into_values().cloned().collect();
+        diff
+    }
+
+    /// Whether runs that were already judged changed or disappeared.
+    pub fn is_rejudge(&self) -> bool {
+        !self.changed.is_empty() || !self.deleted.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &SnapshotDiff::default()
+    }
+
+    /// The changes as events of the snapshot seen at `time`, numbered from `first_id`.
+    /// The contest, problems and teams come before the runs that refer to them.
+    pub fn events(&self, first_id: u64, time: TimeFile) -> Vec<EventRecord> {
+        let contest = self.contest.iter().cloned().map(ContestEvent::Contest);
+        let problems = self.problems.iter().cloned().map(ContestEvent::Problem);
+        let teams = self.teams.iter().cloned().map(ContestEvent::Team);
+        let teams_removed = self.teams_removed.iter().cloned().map(ContestEvent::TeamRemoved);
+        let frozen = self
+            .frozen
+            .iter()
+            .map(|&f| if f { ContestEvent::Frozen } else { ContestEvent::Unfrozen });
+        let new_runs = self.new_runs.iter().cloned().map(ContestEvent::NewRun);
+        let judged = self.judged.iter().cloned().map(ContestEvent::RunJudged);
+        let changed = self
+            .changed
+            .iter()
+            .cloned()
+            .map(|(before, after)| ContestEvent::VerdictChanged { before, after });
+        let deleted = self.deleted.iter().cloned().map(ContestEvent::RunDeleted);
+
+        contest
+            .chain(problems)
+            .chain(teams)
+            .chain(teams_removed)
+            .chain(frozen)
+            .chain(new_runs)
+            .chain(judged)
+            .chain(changed)
+            .chain(deleted)
+            .zip(first_id..)
+            .map(|(event, id)| EventRecord { id, time, event })
+            .collect()
+    }
+}
+
+/// What changed in the public runs since the last update.
+enum RunsUpdate {
+    /// The teams whose runs must all be applied again, and the new runs of the other teams.
+    /// `appended` when nothing is rebuilt and the new runs are later than all the previous ones.
+    /// `since` is the time of the earliest run that changed.
+    Incremental {
+        rebuild: BTreeSet<String>,
+        new_runs: Vec<RunTuple>,
+        appended: bool,
+        since: Minutes,
+    },
+    /// A judged run changed or disappeared, or the contest itself changed.
+    Rebuild,
 }
 
+/// Display order, teams, problems and first accepted run of each problem.
+pub type Scoreboard<'a> = (
+    &'a Vec<String>,
+    &'a BTreeMap<String, Team>,
+    &'a Vec<ProblemInfo>,
+    &'a BTreeMap<String, FirstSolve>,
+);
+
 pub fn read_contest(s: &String) -> ContestIOResult<ContestFile> {
     ContestFile::from_string(s)
 }
@@ -179,21 +344,32 @@ pub fn read_runs(s: &String) -> ContestIOResult<RunsFile> {
     RunsFile::from_string(s)
 }
 
+/// Reads the config and checks the sede codes against each other, so a broken
+/// hierarchy stops the program at startup. The warnings are reported on the first update.
+pub fn read_config(path: &str) -> ContestIOResult<ContestConfig> {
+    let s = read_to_string(path)?;
+    let config: ContestConfig = serde_json::from_str(&s)?;
+    config.contest.validate_codes()?;
+    Ok(config)
+}
+
 
 impl DB {
     pub fn latest(&self) -> Vec<RunsPanelItem> {
         self.run_file
             .sorted()
             .into_iter()
-            .filter( |r| r.time < self.contest_file.score_freeze_time)
             // .take(n)
             .map(|r| {
                 let dummy = Team::dummy();
                 let t = self.contest_file.teams.get(&r.team_login).unwrap_or(&dummy);
+                let first = self.contest_file.first_solved.get(&r.prob)
+                    .map(|f| f.run_id == r.id)
+                    .unwrap_or(false);
                 RunsPanelItem {
                     id: r.id,
                     placement: t.placement,
-                    color: 0,
+                    color: if first { RunsPanelItem::FIRST_SOLVE } else { 0 },
                     escola: t.escola.clone(),
                     team_name: t.name.clone(),
                     team_login: t.login.clone(),
@@ -205,43 +381,294 @@ impl DB {
     }
 
     pub fn empty() -> Self {
+        Self::with_config(ContestConfig::default())
+    }
+
+    pub fn with_config(config: ContestConfig) -> Self {
         DB {
             run_file: RunsFile::empty(),
             run_file_secret: RunsFile::empty(),
             contest_file_begin: ContestFile::dummy(),
             contest_file: ContestFile::dummy(),
-            time_file: 0,
+            time_file: Seconds(0),
+            config,
+            sede_rankings: BTreeMap::new(),
+            rank_history: BTreeMap::new(),
+            scores: ScoreCache::default(),
+            events: Vec::new(),
+            event_limit: EVENT_LOG_LIMIT,
+            updates: broadcast::channel(16).0,
+            start_time: None,
         }
     }
 
-    pub fn get_scoreboard(&self) -> (&Vec<String>, &BTreeMap<String, Team>, usize) {
+    /// Receives an `Update` after every successful refresh.
+    pub fn subscribe(&self) -> broadcast::Receiver<Update> {
+        self.updates.subscribe()
+    }
+
+    pub fn get_scoreboard(&self) -> Scoreboard<'_> {
         (
             &self.contest_file.score_board,
             &self.contest_file.teams,
-            self.contest_file.number_problems,
+            &self.contest_file.problems,
+            &self.contest_file.first_solved,
         )
     }
 
+    /// The scoreboard restricted to the teams of a sede, with their local placements.
+    pub fn get_sede_scoreboard(&self, source: &str)
+     -> Option<(&SedeRanking, BTreeMap<&String, &Team>, &Vec<ProblemInfo>)> {
+        let ranking = self.sede_rankings.get(source)?;
+        let teams = ranking.entries.iter()
+            .filter_map(|e| self.contest_file.teams.get_key_value(&e.team_login))
+            .collect();
+        Some((ranking, teams, &self.contest_file.problems))
+    }
+
+    pub fn latest_in_sede(&self, source: &str) -> Option<Vec<RunsPanelItem>> {
+        let ranking = self.sede_rankings.get(source)?;
+        Some(self.latest()
+            .into_iter()
+            .filter(|r| ranking.contains_login(&r.team_login))
+            .map(|mut r| {
+                let first = ranking.first_solved.get(&r.problem)
+                    .map(|f| f.run_id == r.id)
+                    .unwrap_or(false);
+                if first && r.color == 0 {
+                    r.color = RunsPanelItem::FIRST_SOLVE_IN_SEDE;
+                }
+                r
+            })
+            .collect())
+    }
+
+    /// Statistics of a sede, or of the whole contest.
+    /// Without the secret, only the runs before the freeze are counted.
+    pub fn get_stats(&self, source: &Option<String>, secret: bool) -> ContestIOResult<ContestStats> {
+        let all_runs;
+        let (contest, runs) = if secret {
+            all_runs = self.contest_file_begin.at_time(&self.run_file_secret, Minutes(i64::MAX))?;
+            (&all_runs, &self.run_file_secret)
+        } else {
+            (&self.contest_file, &self.run_file)
+        };
+        match source {
+            None => Ok(ContestStats::new(contest, runs, |_| true)),
+            Some(source) => {
+                let sedes = &self.config.contest;
+                let sede = sedes
+                    .sede(source)
+                    .ok_or_else(|| ContestIOError::Info(format!("Unknown sede {}", source)))?;
+                Ok(ContestStats::new(contest, runs, |t| sedes.sede_contains_login(sede, &t.login)))
+            },
+        }
+    }
+
+    pub fn awards(&self) -> Awards {
+        self.config.contest.awards(&self.contest_file)
+    }
+
+    /// The awards of the final scoreboard, including the verdicts hidden by the freeze.
+    pub fn awards_secret(&self) -> ContestIOResult<Awards> {
+        let contest = self.contest_at(self.contest_file_begin.maximum_time + Minutes(1), true)?;
+        Ok(self.config.contest.awards(&contest))
+    }
+
+    /// The teams that advance according to the qualification rules of the config.
+    pub fn qualified(&self) -> ContestIOResult<Vec<Qualified>> {
+        Ok(self.config.qualification.qualify(&self.config.contest, &self.contest_file)?)
+    }
+
+    /// The teams that advance on the final scoreboard, including the verdicts hidden by the freeze.
+    pub fn qualified_secret(&self) -> ContestIOResult<Vec<Qualified>> {
+        let contest = self.contest_at(self.contest_file_begin.maximum_time + Minutes(1), true)?;
+        Ok(self.config.qualification.qualify(&self.config.contest, &contest)?)
+    }
+
+    /// Rank history of the given teams, skipping unknown logins.
+    pub fn get_history<'a, I>(&self, logins: I) -> BTreeMap<&String, &Vec<RankPoint>>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        logins
+            .filter_map(|login| self.rank_history.get_key_value(login))
+            .collect()
+    }
+
+    /// The contest with only the runs before `time`.
+    /// Without the secret, only times up to the score freeze are allowed.
+    pub fn contest_at(&self, time: Minutes, secret: bool) -> ContestIOResult<ContestFile> {
+        let runs = if secret {
+            &self.run_file_secret
+        } else if time <= self.contest_file_begin.score_freeze_time {
+            &self.run_file
+        } else {
+            return Err(ContestIOError::Info(format!("Time {} is after the score freeze", time)));
+        };
+        Ok(self.contest_file_begin.at_time(runs, time)?)
+    }
+
     pub fn recalculate_score(&mut self) -> Result<(), ContestError> {
         self.contest_file = self.contest_file_begin.clone();
         for r in self.run_file.sorted() {
             self.contest_file.apply_run(r)?;
         }
-        self.contest_file.reload_score()
+        self.scores.clear();
+        self.contest_file.recalculate_placement_with(&mut self.scores)?;
+        self.sede_rankings = self.config.contest.rankings(&self.contest_file);
+        self.rank_history = self.contest_file_begin.rank_history(&self.run_file)?;
+        Ok(())
     }
 
-    pub fn refresh_db(&mut self, time: i64, contest: ContestFile, runs: RunsFile) -> Result<(), ContestError> {
+    /// Decides how to update the scoreboard from the changes of the public runs.
+    /// A pending run that got its verdict, or a run older than the last one of its team,
+    /// only rebuilds that team; a judged run that changed means a rejudge.
+    fn runs_update(&self, contest: &ContestFile, diff: &SnapshotDiff) -> RunsUpdate {
+        let begin = &self.contest_file_begin;
+        let same_contest = contest.scoring_rules == begin.scoring_rules
+            && contest.problems == begin.problems
+            && contest.teams.keys().eq(begin.teams.keys());
+        if !same_contest || diff.is_rejudge() {
+            return RunsUpdate::Rebuild;
+        }
+
+        let mut last_time: BTreeMap<&String, Minutes> = BTreeMap::new();
+        for r in self.run_file.sorted() {
+            let t = last_time.entry(&r.team_login).or_insert(r.time);
+            *t = r.time.max(*t);
+        }
+        let latest = last_time.values().max().cloned();
+
+        let mut rebuild: BTreeSet<String> = diff.judged.iter().map(|r| r.team_login.clone()).collect();
+        let mut new_runs = Vec::new();
+        for r in &diff.new_runs {
+            match last_time.get(&r.team_login) {
+                Some(t) if r.time <= *t => {
+                    rebuild.insert(r.team_login.clone());
+                },
+                _ => new_runs.push(r.clone()),
+            }
+        }
+        let appended = rebuild.is_empty() && new_runs.iter().all(|r| latest.map(|t| r.time > t).unwrap_or(true));
+        let since = diff.judged.iter().chain(diff.new_runs.iter()).map(|r| r.time).min().unwrap_or_default();
+        RunsUpdate::Incremental { rebuild, new_runs, appended, since }
+    }
+
+    /// Applies only what changed since the last update on top of the current scoreboard.
+    fn update_score(&mut self, rebuild: BTreeSet<String>, new_runs: Vec<RunTuple>, appended: bool, since: Minutes) -> Result<(), ContestError> {
+        let mut previous = std::mem::replace(&mut self.contest_file, self.contest_file_begin.clone());
+        for team in self.contest_file.teams.values_mut() {
+            if rebuild.contains(&team.login) {
+                continue;
+            }
+            if let Some(p) = previous.teams.remove(&team.login) {
+                team.problems = p.problems;
+            }
+        }
+
+        if appended {
+            self.contest_file.apply_runs_with_history(&new_runs, &mut self.scores, &mut self.rank_history)?;
+        } else {
+            for r in self.run_file.sorted().iter().filter(|r| rebuild.contains(&r.team_login)) {
+                self.contest_file.apply_run(r)?;
+            }
+            for r in new_runs.iter().filter(|r| !rebuild.contains(&r.team_login)) {
+                self.contest_file.apply_run(r)?;
+            }
+            for login in rebuild.iter().chain(new_runs.iter().map(|r| &r.team_login)) {
+                self.scores.invalidate(login);
+            }
+            self.contest_file.recalculate_placement_with(&mut self.scores)?;
+            self.contest_file_begin.update_rank_history(&self.run_file, since, &mut self.rank_history)?;
+        }
+
+        if !new_runs.is_empty() || !rebuild.is_empty() {
+            self.sede_rankings = self.config.contest.rankings(&self.contest_file);
+        }
+        Ok(())
+    }
+
+    /// Replaces the contest and the runs, updating the scoreboard incrementally when possible.
+    pub fn refresh_db(&mut self, time: TimeFile, mut contest: ContestFile, runs: RunsFile) -> Result<(), ContestError> {
+        self.config.apply(&mut contest);
+        let public = runs.publish(&contest);
+        let diff = SnapshotDiff::new(&self.contest_file_begin, &self.run_file, &contest, &public);
+        let update = self.runs_update(&contest, &diff);
+        let first_id = self.events.last().map(|e| e.id + 1).unwrap_or(1);
+        self.events.extend(diff.events(first_id, time));
+        if self.events.len() > self.event_limit {
+            let dropped = self.events.len() - self.event_limit;
+            self.events.drain(..dropped);
+        }
+
+        if self.start_time.is_none() {
+            self.start_time = Some(self.configured_start_time().unwrap_or_else(|| {
+                OffsetDateTime::now_utc() - Duration::seconds(time.0)
+            }));
+        }
         self.time_file = time;
         self.contest_file_begin = contest;
-        self.run_file = runs.filter_frozen(self.contest_file_begin.score_freeze_time);
+        self.run_file = public;
         self.run_file_secret = runs;
 
-        
-        self.recalculate_score()
+        match update {
+            RunsUpdate::Incremental { rebuild, new_runs, appended, since } => {
+                self.update_score(rebuild, new_runs, appended, since)?
+            },
+            RunsUpdate::Rebuild => self.recalculate_score()?,
+        }
+
+        let first = self.events.partition_point(|e| e.id < first_id);
+        // fails only when no display is listening
+        let _ = self.updates.send(Update {
+            timer: self.timer_data(),
+            events: self.events[first..].to_vec(),
+        });
+        Ok(())
+    }
+
+    /// The events after the one numbered `since` that are still in the log.
+    pub fn events_since(&self, since: u64) -> &[EventRecord] {
+        let first = self.events.partition_point(|e| e.id <= since);
+        &self.events[first..]
+    }
+
+    /// The events after the one numbered `since`, as newline-delimited JSON.
+    pub fn feed(&self, since: u64) -> String {
+        self.events_since(since)
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap() + "\n")
+            .collect()
+    }
+
+    /// Checks the sedes of the config against the teams of the last contest file.
+    pub fn validate_config(&self) -> ContestIOResult<Vec<String>> {
+        let mut warnings = self.config.contest.validate(&self.contest_file_begin)?;
+        if self.config.start_time.is_some() && self.configured_start_time().is_none() {
+            warnings.push("start_time is not in RFC 3339, it will be estimated".to_string());
+        }
+        Ok(warnings)
+    }
+
+    fn configured_start_time(&self) -> Option<OffsetDateTime> {
+        let start = self.config.start_time.as_ref()?;
+        OffsetDateTime::parse(start, Format::Rfc3339).ok()
+    }
+
+    /// The public data in the shape of the Contest API.
+    pub fn contest_api(&self) -> ContestApi<'_> {
+        ContestApi {
+            id: self.config.contest_id.as_deref().unwrap_or("contest"),
+            contest: &self.contest_file,
+            runs: &self.run_file,
+            time: self.time_file,
+            start: self.start_time.unwrap_or_else(OffsetDateTime::unix_epoch),
+        }
     }
 
     pub fn timer_data(&self) -> TimerData {
-        TimerData::new(self.time_file, self.contest_file_begin.score_freeze_time)
+        TimerData::new(self.time_file, self.contest_file_begin.score_freeze_time, self.contest_file_begin.maximum_time)
     }
 }
 
@@ -249,6 +676,24 @@ impl DB {
 mod tests {
 
     use super::*;
+    use maratona_animeitor_rust::data::TieBreaker;
+
+    const FIXTURE: &str = "test/webcast_zip_1a_fase_2020";
+
+    /// The contest and the runs of the 1a fase of 2020.
+    fn fixture() -> Result<(ContestFile, RunsFile), ContestIOError> {
+        let contest = ContestFile::from_file(&format!("{}/contest", FIXTURE))?;
+        let runs = RunsFile::from_file(&format!("{}/runs", FIXTURE))?;
+        Ok((contest, runs))
+    }
+
+    /// A database refreshed once with the 1a fase of 2020, and the files it was read from.
+    fn fixture_db(config: ContestConfig) -> Result<(DB, ContestFile, RunsFile), ContestIOError> {
+        let (contest, runs) = fixture()?;
+        let mut db = DB::with_config(config);
+        db.refresh_db(Seconds(0), contest.clone(), runs.clone())?;
+        Ok((db, contest, runs))
+    }
 
     #[test]
     fn test_from_string() -> Result<(), ContestIOError> {
@@ -256,10 +701,22 @@ mod tests {
         let t = RunTuple::from_string(x)?;
 
         assert_eq!(t.id, 375971416);
-        assert_eq!(t.time, 299);
+        assert_eq!(t.time, Minutes(299));
         assert_eq!(t.team_login, "teambrbr3");
         assert_eq!(t.prob, "B");
-        assert_eq!(t.answer, Answer::No);
+        assert_eq!(t.answer, Answer::No(Verdict::Rejected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_string_verdict() -> Result<(), ContestIOError> {
+        let t = RunTuple::from_string("375971416299teambrbr3BCE")?;
+        assert_eq!(t.answer, Answer::No(Verdict::CompilationError));
+
+        let t = RunTuple::from_string("375971416299teambrbr3BTLE")?;
+        assert_eq!(t.answer, Answer::No(Verdict::TimeLimitExceeded));
+
+        assert!(RunTuple::from_string("375971416299teambrbr3BX").is_err());
         Ok(())
     }
 
@@ -285,9 +742,10 @@ mod tests {
         assert_eq!(runs.len(), 6285);
 
         let mut db = DB::empty();
-        db.refresh_db(0, contest, runs)?;
+        db.refresh_db(Seconds(0), contest, runs)?;
 
-        assert_eq!(db.run_file.len(), 4927);
+        assert_eq!(db.run_file.len(), 6285);
+        assert_eq!(db.run_file.sorted().iter().filter(|r| r.answer != Answer::Wait).count(), 4927);
         assert_eq!(db.run_file_secret.len(), 6285);
 
 
@@ -364,11 +822,444 @@ mod tests {
     fn test_parse_contest_file() -> Result<(), ContestIOError> {
         let x = ContestFile::from_file("test/sample/contest")?;
         assert_eq!(x.contest_name, "LATAM ACM ICPC".to_string());
-        assert_eq!(x.maximum_time, 300);
-        assert_eq!(x.current_time, 285);
-        assert_eq!(x.score_freeze_time, 240);
-        assert_eq!(x.penalty_per_wrong_answer, 20);
+        assert_eq!(x.maximum_time, Minutes(300));
+        assert_eq!(x.current_time, Minutes(285));
+        assert_eq!(x.score_freeze_time, Minutes(240));
+        assert_eq!(x.scoring_rules.penalty_per_wrong_answer, 20);
         assert_eq!(x.teams.keys().len(), 72);
+        assert_eq!(x.problems.len(), 13);
+        assert_eq!(x.problems[12].label, "M");
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_problems() -> Result<(), ContestIOError> {
+        let config = read_config("test/config.json")?;
+        let (db, _, _) = fixture_db(config)?;
+
+        let (_, _, problems, _) = db.get_scoreboard();
+        assert_eq!(problems.len(), 16);
+        assert_eq!(problems[1].name, "Problema B");
+        assert_eq!(problems[1].color, Some("#ffd700".to_string()));
+        assert_eq!(db.contest_file_begin.visible_problems().count(), 15);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_scoring_rules() -> Result<(), ContestIOError> {
+        let config: ContestConfig = serde_json::from_str(r#"{"scoring_rules": {
+            "penalty_per_wrong_answer": 10,
+            "ranking": "Unique",
+            "tie_breakers": ["Solved", "Submissions", "Penalty"]
+        }}"#)?;
+        let (db, _, _) = fixture_db(config)?;
+
+        let rules = &db.contest_file_begin.scoring_rules;
+        assert_eq!(rules.penalty_per_wrong_answer, 10);
+        assert_eq!(rules.tie_breakers, vec![TieBreaker::Solved, TieBreaker::Submissions, TieBreaker::Penalty]);
+        assert!(rules.count_solve_time);
+
+        let (score_board, teams, _, _) = db.get_scoreboard();
+        for (i, login) in score_board.iter().enumerate() {
+            assert_eq!(teams[login].placement, i + 1);
+        }
+        for w in score_board.windows(2) {
+            assert!(teams[&w[0]].score(rules) <= teams[&w[1]].score(rules));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_sedes() -> Result<(), ContestIOError> {
+        let config = read_config("../config/maratona.json")?;
+        let (db, _, _) = fixture_db(config)?;
+
+        let sedes = &db.config.contest.sedes;
+        assert_eq!(sedes.len(), 31);
+        let al = sedes.iter().find(|s| s.source == "al").unwrap();
+        assert!(al.contains_login("teambral001"));
+        assert!(!al.contains_login("teambrsp037"));
+
+        let warnings = db.validate_config()?;
+        assert!(warnings.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_config_rejects_duplicated_codes() {
+        match read_config("test/config_duplicated_sedes.json") {
+            Err(ContestIOError::Config(ConfigError::Invalid(errors))) => assert_eq!(errors.len(), 1),
+            r => panic!("expected the duplicated code, got {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_config_sedes_unmatched() -> Result<(), ContestIOError> {
+        let config : ContestConfig = serde_json::from_str(
+            r#"{"sedes": [{"name": "Marte", "source": "Marte", "parent_source": "Brasil", "codes": ["teambrxx"]}]}"#)?;
+        let (db, _, _) = fixture_db(config)?;
+        assert!(db.validate_config().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sede_rankings() -> Result<(), ContestIOError> {
+        let config = read_config("../config/maratona.json")?;
+        let (db, _, _) = fixture_db(config)?;
+
+        let (ranking, teams, _) = db.get_sede_scoreboard("Snordeste").unwrap();
+        let expected = db.contest_file_begin.teams.keys()
+            .filter(|l| ["teambral", "teambrma", "teambrpe", "teambrrn", "teambrse"]
+                .iter().any(|c| l.starts_with(c)))
+            .count();
+        assert_eq!(ranking.entries.len(), expected);
+        assert_eq!(teams.len(), expected);
+        assert_eq!(ranking.entries[0].local_placement, 1);
+        for w in ranking.entries.windows(2) {
+            assert!(w[0].local_placement <= w[1].local_placement);
+            assert!(w[0].global_placement <= w[1].global_placement);
+        }
+
+        let brasil = &db.sede_rankings["Brasil"];
+        assert_eq!(brasil.entries.len(), db.contest_file_begin.teams.len());
+        for e in &brasil.entries {
+            assert_eq!(e.local_placement, e.global_placement);
+        }
+
+        assert!(db.get_sede_scoreboard("Marte").is_none());
+        let latest = db.latest_in_sede("al").unwrap();
+        assert!(latest.iter().all(|r| r.team_login.starts_with("teambral")));
+
+        let stats = db.get_stats(&Some("Snordeste".to_string()), false)?;
+        assert_eq!(stats.teams, expected);
+        let all = db.get_stats(&None, false)?;
+        for (s, a) in stats.problems.iter().zip(all.problems.iter()) {
+            assert_eq!(s.label, a.label);
+            assert!(s.solved <= a.solved && s.attempts <= a.attempts);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_latest_marks_first_solves() -> Result<(), ContestIOError> {
+        let (db, _, _) = fixture_db(ContestConfig::default())?;
+
+        let (_, _, _, first_solved) = db.get_scoreboard();
+        let latest = db.latest();
+        let marked: Vec<_> = latest.iter().filter(|r| r.color == RunsPanelItem::FIRST_SOLVE).collect();
+        assert!(marked.iter().all(|r| first_solved[&r.problem].run_id == r.id));
+        Ok(())
+    }
+
+    #[test]
+    fn test_contest_at_1a_fase_2020() -> Result<(), ContestIOError> {
+        let (db, contest, _) = fixture_db(ContestConfig::default())?;
+        let freeze = contest.score_freeze_time;
+
+        let start = db.contest_at(Minutes(0), false)?;
+        assert!(start.teams.values().all(|t| t.problems.is_empty()));
+
+        let frozen = db.contest_at(freeze, false)?;
+        let (score_board, teams, _, _) = db.get_scoreboard();
+        assert_eq!(&frozen.score_board, score_board);
+        for (login, team) in teams {
+            assert_eq!(frozen.teams[login].placement, team.placement);
+        }
+
+        assert!(db.contest_at(freeze + Minutes(1), false).is_err());
+        let end = db.contest_at(db.contest_file_begin.maximum_time + Minutes(1), true)?;
+        let solved = |c: &ContestFile| c.teams.values()
+            .map(|t| t.problems.values().filter(|p| p.solved).count())
+            .sum::<usize>();
+        assert!(solved(&end) >= solved(&frozen));
+        Ok(())
+    }
+
+    #[test]
+    fn test_frozen_runs_are_pending() -> Result<(), ContestIOError> {
+        let (db, contest, _) = fixture_db(ContestConfig::default())?;
+        let freeze = contest.score_freeze_time;
+
+        assert_eq!(db.run_file.len(), db.run_file_secret.len());
+        let frozen = db.run_file.sorted().iter().filter(|r| r.time >= freeze).count();
+        assert!(frozen > 0);
+        for r in db.run_file.sorted() {
+            assert_eq!(r.answer == Answer::Wait, r.time >= freeze);
+        }
+        assert!(db.latest().iter().any(|r| r.result == Answer::Wait));
+
+        let (_, teams, _, _) = db.get_scoreboard();
+        let pending: usize = teams.values()
+            .flat_map(|t| t.problems.values())
+            .map(|p| p.tries() - p.submissions)
+            .sum();
+        assert!(pending > 0);
+        assert!(teams.values().any(|t| t.wait()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_qualified_secret_counts_frozen_runs() -> Result<(), ContestIOError> {
+        let mut config = read_config("../config/maratona.json")?;
+        config.qualification.wildcards = 10;
+        let (db, contest, _) = fixture_db(config)?;
+
+        let logins = |q: Vec<Qualified>| q.into_iter().map(|q| q.team_login).collect::<Vec<_>>();
+        let final_contest = db.contest_at(contest.maximum_time + Minutes(1), true)?;
+        let expected = logins(db.config.qualification.qualify(&db.config.contest, &final_contest)?);
+        let secret = logins(db.qualified_secret()?);
+        assert_eq!(secret.len(), 10);
+        assert_eq!(secret, expected);
+        assert_ne!(secret, logins(db.qualified()?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verdicts_hidden_after_answer_freeze() -> Result<(), ContestIOError> {
+        let (mut contest, runs) = fixture()?;
+        contest.answer_freeze_time = Some(Minutes(200));
+
+        let mut db = DB::empty();
+        db.refresh_db(Seconds(0), contest, runs)?;
+
+        for r in db.run_file.sorted() {
+            assert_eq!(r.answer == Answer::Wait, r.time >= Minutes(200));
+        }
+        let (_, teams, _, _) = db.get_scoreboard();
+        assert!(teams.values()
+            .flat_map(|t| t.problems.values())
+            .all(|p| !p.solved || p.time_solved < 200));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verdict_delay() -> Result<(), ContestIOError> {
+        let (mut contest, runs) = fixture()?;
+        contest.current_time = Minutes(150);
+        let config = ContestConfig {
+            verdict_delay: Some(Minutes(10)),
+            ..ContestConfig::default()
+        };
+
+        let mut db = DB::with_config(config);
+        db.refresh_db(Seconds(0), contest, runs)?;
+
+        let runs: Vec<_> = db.run_file.sorted().iter().filter(|r| r.time < Minutes(150)).collect();
+        assert!(runs.iter().any(|r| r.time >= Minutes(140)));
+        for r in runs {
+            assert_eq!(r.answer == Answer::Wait, r.time > Minutes(140));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_diff() -> Result<(), ContestIOError> {
+        let teams = vec![Team::new("teama", "A", "a"), Team::new("teamb", "B", "b")];
+        let before = ContestFile::new(
+            "Teste".to_string(), teams, Minutes(0), Minutes(300), Minutes(240),
+            ScoringRules::default(), ProblemInfo::numbered(2));
+        let mut after = before.clone();
+        after.current_time = Minutes(250);
+        after.teams.remove("teama");
+        after.teams.get_mut("teamb").unwrap().name = "b2".to_string();
+        after.teams.insert("teamc".to_string(), Team::new("teamc", "C", "c"));
+
+        let before_runs = RunsFile::from_string("1\x1c10\x1cteama\x1cA\x1c?\n2\x1c20\x1cteamb\x1cA\x1cN\n3\x1c30\x1cteamb\x1cB\x1cY")?;
+        let after_runs = RunsFile::from_string("1\x1c10\x1cteama\x1cA\x1cY\n2\x1c20\x1cteamb\x1cA\x1cY\n4\x1c40\x1cteamc\x1cB\x1cN")?;
+
+        assert!(SnapshotDiff::new(&before, &before_runs, &before, &before_runs).is_empty());
+
+        let diff = SnapshotDiff::new(&before, &before_runs, &after, &after_runs);
+        let ids = |runs: &Vec<RunTuple>| runs.iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(diff.contest, None);
+        assert!(diff.problems.is_empty());
+        assert_eq!(ids(&diff.new_runs), vec![4]);
+        assert_eq!(ids(&diff.judged), vec![1]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0.answer, Answer::No(Verdict::Rejected));
+        assert_eq!(diff.changed[0].1.answer, Answer::Yes(20));
+        assert_eq!(ids(&diff.deleted), vec![3]);
+        let logins: Vec<_> = diff.teams.iter().map(|t| t.login.as_str()).collect();
+        assert_eq!(logins, vec!["teamb", "teamc"]);
+        assert_eq!(diff.teams_removed, vec!["teama"]);
+        assert_eq!(diff.frozen, Some(true));
+        assert!(diff.is_rejudge());
+
+        let events = diff.events(7, Seconds(100));
+        assert_eq!(events.len(), 8);
+        assert_eq!(events[0].id, 7);
+        assert_eq!(events[0].event, ContestEvent::Team(after.teams["teamb"].clone()));
+        assert_eq!(events[3].event, ContestEvent::Frozen);
+        assert!(events.iter().all(|e| e.time == Seconds(100)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_log_1a_fase_2020() -> Result<(), ContestIOError> {
+        let (contest, runs) = fixture()?;
+        let first_yes = runs.sorted().iter().position(|r| matches!(r.answer, Answer::Yes(_))).unwrap();
+
+        let mut db = DB::empty();
+        db.refresh_db(Seconds(100), contest.clone(), runs.clone())?;
+        let count = |db: &DB, f: fn(&ContestEvent) -> bool| db.events.iter().filter(|e| f(&e.event)).count();
+        assert_eq!(count(&db, |e| matches!(e, ContestEvent::Contest(_))), 1);
+        assert_eq!(count(&db, |e| matches!(e, ContestEvent::Problem(_))), contest.problems.len());
+        assert_eq!(count(&db, |e| matches!(e, ContestEvent::Team(_))), contest.teams.len());
+        assert_eq!(count(&db, |e| matches!(e, ContestEvent::Frozen)), 1);
+        assert_eq!(count(&db, |e| matches!(e, ContestEvent::NewRun(_))), runs.len());
+
+        let logged = db.events.len();
+        db.refresh_db(Seconds(130), contest.clone(), runs.clone())?;
+        assert_eq!(db.events.len(), logged);
+
+        let mut rejudged = runs.sorted().clone();
+        rejudged[first_yes].answer = Answer::No(Verdict::WrongAnswer);
+        db.refresh_db(Seconds(160), contest, RunsFile::new(rejudged))?;
+
+        let last = db.events.last().unwrap();
+        assert_eq!(last.id as usize, db.events.len());
+        assert_eq!(last.time, Seconds(160));
+        match &last.event {
+            ContestEvent::VerdictChanged { before, after } => {
+                assert_eq!(before, &runs.sorted()[first_yes]);
+                assert_eq!(after.answer, Answer::No(Verdict::WrongAnswer));
+            },
+            e => panic!("unexpected event {:?}", e),
+        }
+
+        let feed = db.feed(logged as u64);
+        let lines: Vec<_> = feed.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(&serde_json::from_str::<EventRecord>(lines[0]).unwrap(), last);
+        assert_eq!(db.feed(0).lines().count(), db.events.len());
+        assert!(db.feed(last.id).is_empty());
+        assert_eq!(db.events_since(last.id - 1), &[last.clone()][..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_log_is_bounded() -> Result<(), ContestIOError> {
+        let (contest, runs) = fixture()?;
+        let first_yes = runs.sorted().iter().position(|r| matches!(r.answer, Answer::Yes(_))).unwrap();
+
+        let mut db = DB::empty();
+        db.event_limit = 10;
+        db.refresh_db(Seconds(100), contest.clone(), runs.clone())?;
+        let logged = db.events.last().unwrap().id;
+        assert_eq!(db.events.len(), 10);
+        assert_eq!(db.events[0].id, logged - 9);
+        assert_eq!(db.events_since(0).len(), 10);
+
+        let mut rejudged = runs.sorted().clone();
+        rejudged[first_yes].answer = Answer::No(Verdict::WrongAnswer);
+        db.refresh_db(Seconds(160), contest, RunsFile::new(rejudged))?;
+        assert_eq!(db.events.len(), 10);
+        assert_eq!(db.events_since(logged).len(), 1);
+        assert_eq!(db.events.last().unwrap().id, logged + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_updates_are_pushed() -> Result<(), ContestIOError> {
+        let (contest, runs) = fixture()?;
+
+        let mut db = DB::empty();
+        let mut updates = db.subscribe();
+        db.refresh_db(Seconds(100), contest.clone(), runs.clone())?;
+        let update = updates.try_recv().unwrap();
+        assert_eq!(update.timer.current_time, Seconds(100));
+        assert_eq!(update.events, db.events);
+
+        db.refresh_db(Seconds(130), contest, runs)?;
+        let update = updates.try_recv().unwrap();
+        assert_eq!(update.timer.current_time, Seconds(130));
+        assert!(update.events.is_empty());
+        assert!(updates.try_recv().is_err());
+        Ok(())
+    }
+
+    fn fresh_db(contest: &ContestFile, runs: Vec<RunTuple>) -> Result<DB, ContestError> {
+        let mut db = DB::empty();
+        db.refresh_db(Seconds(0), contest.clone(), RunsFile::new(runs))?;
+        Ok(db)
+    }
+
+    fn assert_same_score(incremental: &DB, fresh: &DB) {
+        assert_eq!(
+            serde_json::to_string(&incremental.contest_file).unwrap(),
+            serde_json::to_string(&fresh.contest_file).unwrap()
+        );
+        assert_eq!(incremental.rank_history, fresh.rank_history);
+    }
+
+    #[test]
+    fn test_incremental_update_1a_fase_2020() -> Result<(), ContestIOError> {
+        let (contest, runs) = fixture()?;
+
+        let mut db = DB::empty();
+        for end in &[60, 150, 151, 300] {
+            let partial: Vec<_> = runs.sorted().iter().filter(|r| r.time < Minutes(*end)).cloned().collect();
+            db.refresh_db(Seconds(0), contest.clone(), RunsFile::new(partial.clone()))?;
+            assert_same_score(&db, &fresh_db(&contest, partial)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_update_rejudge() -> Result<(), ContestIOError> {
+        let (contest, runs) = fixture()?;
+        let first_yes = runs.sorted().iter().position(|r| matches!(r.answer, Answer::Yes(_))).unwrap();
+
+        let mut rejudged = runs.sorted().clone();
+        rejudged[first_yes].answer = Answer::No(Verdict::WrongAnswer);
+        let mut db = fresh_db(&contest, rejudged.clone())?;
+
+        rejudged[first_yes] = runs.sorted()[first_yes].clone();
+        db.refresh_db(Seconds(0), contest.clone(), RunsFile::new(rejudged.clone()))?;
+        assert_same_score(&db, &fresh_db(&contest, rejudged)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_update_pending_judged() -> Result<(), ContestIOError> {
+        let (contest, runs) = fixture()?;
+
+        let mut pending = runs.sorted().clone();
+        for r in pending.iter_mut().filter(|r| r.time < Minutes(240)).step_by(50) {
+            r.answer = Answer::Wait;
+        }
+        let mut db = fresh_db(&contest, pending)?;
+
+        db.refresh_db(Seconds(0), contest.clone(), runs.clone())?;
+        assert_same_score(&db, &fresh_db(&contest, runs.sorted().clone())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_teams() -> Result<(), ContestIOError> {
+        let config = read_config("test/config.json")?;
+        let (db, _, _) = fixture_db(config)?;
+
+        let (score_board, teams, _, _) = db.get_scoreboard();
+        let ufal = &teams["teambral001"];
+        assert_eq!(ufal.escola, "UFAL");
+        assert_eq!(ufal.institution, "Universidade Federal de Alagoas");
+        assert!(ufal.has_category("women"));
+        assert_eq!(ufal.logo_path(), Some("/static/assets/logos/ufal.png".to_string()));
+
+        let guest = &teams["teambrsp037"];
+        assert!(!guest.official);
+        assert_eq!(guest.placement, 0);
+        assert!(score_board.contains(&guest.login));
+
+        let mut placements: Vec<_> = teams
+            .values()
+            .filter(|t| t.official)
+            .map(|t| t.placement)
+            .collect();
+        placements.sort();
+        assert_eq!(placements[0], 1);
+        assert!(*placements.last().unwrap() < teams.len());
         Ok(())
     }
 }
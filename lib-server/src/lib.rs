@@ -1,8 +1,13 @@
 pub mod dataio;
+pub mod clics;
+pub mod source;
 extern crate rand;
 extern crate itertools;
 
 use crate::dataio::*;
+use crate::source::{ContestSource, PollConfig};
+use maratona_animeitor_rust::configdata::{ConfigError, ContestConfig};
+use maratona_animeitor_rust::data::{ContestFile, Minutes, RunsFile, TimeFile};
 
 use hyper::Client;
 use hyper_tls::HttpsConnector;
@@ -10,30 +15,102 @@ use hyper_tls::HttpsConnector;
 use hyper::body;
 use std::io::prelude::*;
 use zip;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tokio;
-use tokio::{spawn, sync::Mutex};
+use tokio::{spawn, sync::{broadcast, Mutex}};
+use futures::SinkExt;
 
-use warp::Filter;
+use serde::Deserialize;
+use warp::{Filter, Reply};
 
-pub fn spawn_db_update(data_url : String) -> Arc<Mutex<DB>> {
-    let shared_db = Arc::new(Mutex::new(DB::empty()));
+#[derive(Debug, Deserialize)]
+struct SedeQuery {
+    sede: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// Logins separated by commas.
+    team: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FeedQuery {
+    /// Id of the last event already seen.
+    since: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventFeedQuery {
+    /// Id of the last event already seen.
+    since_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScoreQuery {
+    sede: Option<String>,
+    at: Option<Minutes>,
+}
+
+/// Polls the source in the background, retrying with a backoff when it fails.
+pub fn spawn_db_update(mut source: Box<dyn ContestSource>, config : ContestConfig, poll: PollConfig) -> Arc<Mutex<DB>> {
+    let shared_db = Arc::new(Mutex::new(DB::with_config(config)));
     let cloned_db = shared_db.clone();
     spawn(async move {
-        let dur = tokio::time::Duration::new(30, 0);
-        let mut interval = tokio::time::interval(dur);
+        let mut validated = false;
+        let mut failures = 0;
+        let mut last_update = tokio::time::Instant::now();
+        let mut stale = false;
         loop {
-            interval.tick().await;
-            let r = update_runs(&data_url, cloned_db.clone()).await;
-            match r {
-                Ok(_) => (),
-                Err(e) => eprintln!("Error updating run: {}", e),
-            }
+            let r = update_runs(source.as_mut(), cloned_db.clone()).await;
+            let wait = match r {
+                Ok(_) => {
+                    if stale {
+                        eprintln!("Updating again from {}", source.name());
+                    }
+                    failures = 0;
+                    last_update = tokio::time::Instant::now();
+                    stale = false;
+                    if !validated {
+                        validated = true;
+                        check_config(cloned_db.clone()).await;
+                    }
+                    poll.interval
+                },
+                Err(e) => {
+                    eprintln!("Error updating run: {}", e);
+                    failures += 1;
+                    if !stale && last_update.elapsed() >= poll.stale_after {
+                        stale = true;
+                        eprintln!("No update from {} for {:?}, the data is stale", source.name(), last_update.elapsed());
+                    }
+                    poll.backoff(failures)
+                },
+            };
+            tokio::time::delay_for(wait).await;
         }
     });
     shared_db
 }
 
+pub async fn check_config(shared_db: Arc<Mutex<DB>>) {
+    let db = shared_db.lock().await;
+    match db.validate_config() {
+        Ok(warnings) => {
+            for w in warnings {
+                eprintln!("Config warning: {}", w);
+            }
+        },
+        Err(ContestIOError::Config(ConfigError::Invalid(errors))) => {
+            for e in errors {
+                eprintln!("Invalid config: {}", e);
+            }
+        },
+        Err(e) => eprintln!("Invalid config: {}", e),
+    }
+}
+
 pub fn serve_urlbase(shared_db: Arc<Mutex<DB>>, source: &Option<String>, secret : &String)
  -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
     
@@ -48,6 +125,7 @@ pub fn serve_urlbase(shared_db: Arc<Mutex<DB>>, source: &Option<String>, secret
     // warp::path(source.clone())
     let runs = 
         warp::path("runs")
+        .and(warp::query::<SedeQuery>())
         .and(with_db(shared_db.clone()))
         .and_then(serve_runs);
 
@@ -75,15 +153,120 @@ pub fn serve_urlbase(shared_db: Arc<Mutex<DB>>, source: &Option<String>, secret
 
     let scoreboard = 
         warp::path("score")
-        .and(with_db(shared_db))
+        .and(warp::query::<ScoreQuery>())
+        .and(with_db(shared_db.clone()))
+        .and(warp::any().map(|| false))
+        .and_then(serve_score);
+
+    let scoreboard_secret = 
+        warp::path(format!("score_{}", secret))
+        .and(warp::query::<ScoreQuery>())
+        .and(with_db(shared_db.clone()))
+        .and(warp::any().map(|| true))
         .and_then(serve_score);
 
+    let stats = 
+        warp::path("stats")
+        .and(warp::query::<SedeQuery>())
+        .and(with_db(shared_db.clone()))
+        .and(warp::any().map(|| false))
+        .and_then(serve_stats);
+
+    let stats_secret = 
+        warp::path(format!("stats_{}", secret))
+        .and(warp::query::<SedeQuery>())
+        .and(with_db(shared_db.clone()))
+        .and(warp::any().map(|| true))
+        .and_then(serve_stats);
+
+    let qualified = 
+        warp::path("qualified")
+        .and(with_db(shared_db.clone()))
+        .and(warp::any().map(|| false))
+        .and_then(serve_qualified);
+
+    let qualified_secret = 
+        warp::path(format!("qualified_{}", secret))
+        .and(with_db(shared_db.clone()))
+        .and(warp::any().map(|| true))
+        .and_then(serve_qualified);
+
+    let history = 
+        warp::path("history")
+        .and(warp::query::<HistoryQuery>())
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_history);
+
+    let awards = 
+        warp::path("awards")
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_awards);
+
+    let awards_secret = 
+        warp::path(format!("awards_{}", secret))
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_awards_secret);
+
+    let events = 
+        warp::path("events")
+        .and(warp::query::<FeedQuery>())
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_events);
+
+    let feed = 
+        warp::path("feed")
+        .and(warp::query::<FeedQuery>())
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_feed);
+
+    let updates = 
+        warp::path("updates")
+        .and(warp::ws())
+        .and(with_db(shared_db.clone()))
+        .map(|ws: warp::ws::Ws, db: Shared| ws.on_upgrade(move |socket| push_updates(socket, db)));
+
+    let api_contests = 
+        warp::path!("api" / "contests")
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_api_contests);
+
+    let api_contest = 
+        warp::path!("api" / "contests" / String)
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_api_contest);
+
+    let api_endpoint = 
+        warp::path!("api" / "contests" / String / String)
+        .and(warp::query::<EventFeedQuery>())
+        .and(with_db(shared_db.clone()))
+        .and_then(serve_api_endpoint);
+
+    let sedes = 
+        warp::path("sedes")
+        .and(with_db(shared_db))
+        .and_then(serve_sedes);
+
     let routes = runs
         .or(all_runs)
         .or(all_runs_secret)
         .or(timer)
         .or(contest_file)
-        .or(scoreboard);
+        .or(scoreboard)
+        .or(scoreboard_secret)
+        .or(stats)
+        .or(stats_secret)
+        .or(qualified)
+        .or(qualified_secret)
+        .or(awards)
+        .or(awards_secret)
+        .or(history)
+        .or(events)
+        .or(feed)
+        .or(updates)
+        .or(api_contests)
+        .or(api_contest)
+        .or(api_endpoint)
+        .or(sedes);
 
     match source {
         None => routes.boxed(),
@@ -92,7 +275,7 @@ pub fn serve_urlbase(shared_db: Arc<Mutex<DB>>, source: &Option<String>, secret
 }
 
 
-async fn read_bytes_from_path(path: &String ) -> Result<Vec<u8>, ContestIOError> {
+pub(crate) async fn read_bytes_from_path(path: &String ) -> Result<Vec<u8>, ContestIOError> {
     read_bytes_from_url(path).await
     .or_else(|_| read_bytes_from_file(path) )
 }
@@ -141,15 +324,40 @@ fn read_from_zip(
         // .or_else(|t| try_read_from_zip(zip, name))?
 }
 
-async fn update_runs(uri: &String, runs: Arc<Mutex<DB>>) -> Result<(), ContestIOError> {
+/// Reads the time, contest and runs files of a webcast, from an url or a local file.
+pub async fn read_webcast(uri: &String) -> Result<(TimeFile, ContestFile, RunsFile), ContestIOError> {
     // let zip_data = read_bytes_from_url(uri).await?;
     let zip_data = read_bytes_from_path(uri).await?;
+    read_webcast_zip(&zip_data)
+}
+
+/// Reads the contest from a webcast zip or, when the data is not a zip, from a CLICS event feed.
+pub async fn read_snapshot(uri: &String) -> Result<(TimeFile, ContestFile, RunsFile), ContestIOError> {
+    read_snapshot_data(read_bytes_from_path(uri).await?, live_clock(uri))
+}
 
-    let reader = std::io::Cursor::new(&zip_data);
+/// The wall clock when `uri` is a live url; a local file has none.
+pub fn live_clock(uri: &str) -> Option<time::OffsetDateTime> {
+    (uri.starts_with("http://") || uri.starts_with("https://")).then(time::OffsetDateTime::now_utc)
+}
+
+/// Reads the contest from the contents of a webcast zip or of a CLICS event feed.
+/// `now` places a running event feed in time; without it, the feed is read up to its latest event.
+pub fn read_snapshot_data(data: Vec<u8>, now: Option<time::OffsetDateTime>) -> Result<(TimeFile, ContestFile, RunsFile), ContestIOError> {
+    if data.starts_with(b"PK") {
+        return read_webcast_zip(&data);
+    }
+    let feed = String::from_utf8(data)
+        .map_err(|_| ContestIOError::Info("Could not parse to UTF8".to_string()))?;
+    clics::read_event_feed(&feed, now)
+}
+
+fn read_webcast_zip(zip_data: &Vec<u8>) -> Result<(TimeFile, ContestFile, RunsFile), ContestIOError> {
+    let reader = std::io::Cursor::new(zip_data);
     let mut zip = zip::ZipArchive::new(reader)
         .map_err(|e| ContestIOError::Info(format!("Could not open zipfile: {:?}", e)))?;
 
-    let time_data : i64 = read_from_zip(&mut zip, "time")?.parse()?;
+    let time_data : TimeFile = read_from_zip(&mut zip, "time")?.parse()?;
 
     let contest_data = read_from_zip(&mut zip, "contest")?;
     let contest_data = read_contest(&contest_data)?;
@@ -157,15 +365,26 @@ async fn update_runs(uri: &String, runs: Arc<Mutex<DB>>) -> Result<(), ContestIO
 
     let runs_data = read_from_zip(&mut zip, "runs")?;
     let runs_data = read_runs(&runs_data)?;
+    Ok((time_data, contest_data, runs_data))
+}
+
+async fn update_runs(source: &mut dyn ContestSource, runs: Arc<Mutex<DB>>) -> Result<(), ContestIOError> {
+    let (time_data, contest_data, runs_data) = source.fetch().await?;
 
     let mut db = runs.lock().await;
     db.refresh_db(time_data, contest_data, runs_data)?;
     Ok(())
 }
 
-async fn serve_runs(runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+async fn serve_runs(query: SedeQuery, runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
     let db = runs.lock().await;
-    let r = serde_json::to_string(&*db.latest()).unwrap();
+    let r = match query.sede {
+        None => serde_json::to_string(&*db.latest()).unwrap(),
+        Some(sede) => match db.latest_in_sede(&sede) {
+            None => return Err(warp::reject::not_found()),
+            Some(latest) => serde_json::to_string(&latest).unwrap(),
+        },
+    };
     Ok(r)
 }
 
@@ -194,9 +413,165 @@ async fn serve_contestfile(runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, war
     Ok(r)
 }
 
-async fn serve_score(runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+async fn serve_score(query: ScoreQuery, runs: Arc<Mutex<DB>>, secret: bool) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let at = match query.at {
+        None => None,
+        Some(time) => {
+            match db.contest_at(time, secret) {
+                Ok(contest) => Some(contest),
+                Err(_) => return Err(warp::reject::not_found()),
+            }
+        },
+    };
+    let r = match (query.sede, at) {
+        (None, None) => serde_json::to_string(&db.get_scoreboard()).unwrap(),
+        (Some(sede), None) => match db.get_sede_scoreboard(&sede) {
+            None => return Err(warp::reject::not_found()),
+            Some(score) => serde_json::to_string(&score).unwrap(),
+        },
+        (None, Some(contest)) => serde_json::to_string(&(
+            &contest.score_board,
+            &contest.teams,
+            &contest.problems,
+            &contest.first_solved,
+        )).unwrap(),
+        (Some(sede), Some(contest)) => match db.config.contest.sede(&sede) {
+            None => return Err(warp::reject::not_found()),
+            Some(sede) => {
+                let ranking = db.config.contest.ranking(sede, &contest);
+                let teams: BTreeMap<_, _> = ranking.entries.iter()
+                    .filter_map(|e| contest.teams.get_key_value(&e.team_login))
+                    .collect();
+                serde_json::to_string(&(&ranking, teams, &contest.problems)).unwrap()
+            },
+        },
+    };
+    Ok(r)
+}
+
+async fn serve_stats(query: SedeQuery, runs: Arc<Mutex<DB>>, secret: bool) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    match db.get_stats(&query.sede, secret) {
+        Err(_) => Err(warp::reject::not_found()),
+        Ok(stats) => Ok(serde_json::to_string(&stats).unwrap()),
+    }
+}
+
+async fn serve_qualified(runs: Arc<Mutex<DB>>, secret: bool) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let qualified = if secret { db.qualified_secret() } else { db.qualified() };
+    match qualified {
+        Ok(qualified) => Ok(serde_json::to_string(&qualified).unwrap()),
+        Err(e) => {
+            eprintln!("Error computing qualification: {}", e);
+            Err(warp::reject::not_found())
+        },
+    }
+}
+
+async fn serve_history(query: HistoryQuery, runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let r = serde_json::to_string(&db.get_history(query.team.split(','))).unwrap();
+    Ok(r)
+}
+
+async fn serve_awards(runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let r = serde_json::to_string(&db.awards()).unwrap();
+    Ok(r)
+}
+
+async fn serve_awards_secret(runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    match db.awards_secret() {
+        Ok(awards) => Ok(serde_json::to_string(&awards).unwrap()),
+        Err(_) => Err(warp::reject::not_found()),
+    }
+}
+
+async fn serve_events(query: FeedQuery, runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let r = serde_json::to_string(db.events_since(query.since.unwrap_or(0))).unwrap();
+    Ok(r)
+}
+
+async fn serve_feed(query: FeedQuery, runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let r = db.feed(query.since.unwrap_or(0));
+    Ok(warp::reply::with_header(r, "content-type", "application/x-ndjson"))
+}
+
+/// Sends every update of the DB to the socket, until the display goes away.
+async fn push_updates(mut socket: warp::ws::WebSocket, runs: Arc<Mutex<DB>>) {
+    let mut updates = runs.lock().await.subscribe();
+    loop {
+        let update = match updates.recv().await {
+            Ok(update) => update,
+            // a slow display misses some events, but still reloads on the next update
+            Err(broadcast::RecvError::Lagged(_)) => continue,
+            Err(broadcast::RecvError::Closed) => break,
+        };
+        let message = warp::ws::Message::text(serde_json::to_string(&update).unwrap());
+        if socket.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn json_reply(r: String) -> impl warp::Reply {
+    warp::reply::with_header(r, "content-type", "application/json")
+}
+
+async fn serve_api_contests(runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let r = serde_json::to_string(&[db.contest_api().contest()]).unwrap();
+    Ok(json_reply(r))
+}
+
+async fn serve_api_contest(id: String, runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
+    let db = runs.lock().await;
+    let api = db.contest_api();
+    if id != api.id {
+        return Err(warp::reject::not_found());
+    }
+    Ok(json_reply(serde_json::to_string(&api.contest()).unwrap()))
+}
+
+async fn serve_api_endpoint(id: String, endpoint: String, query: EventFeedQuery, runs: Arc<Mutex<DB>>)
+ -> Result<warp::reply::Response, warp::Rejection> {
+    let db = runs.lock().await;
+    let api = db.contest_api();
+    if id != api.id {
+        return Err(warp::reject::not_found());
+    }
+    let r = match endpoint.as_str() {
+        "state" => serde_json::to_string(&api.state()),
+        "problems" => serde_json::to_string(&api.problems()),
+        "teams" => serde_json::to_string(&api.teams()),
+        "judgement-types" => serde_json::to_string(&api.judgement_types()),
+        "submissions" => serde_json::to_string(&api.submissions()),
+        "judgements" => serde_json::to_string(&api.judgements()),
+        "scoreboard" => serde_json::to_string(&api.scoreboard()),
+        "event-feed" => {
+            let reply = match api.event_feed(&db.events, query.since_id.as_deref()) {
+                Some(feed) => warp::reply::with_header(feed, "content-type", "application/x-ndjson").into_response(),
+                // as in the Contest API, an unknown event id is a bad request
+                None => warp::reply::with_status(
+                    "unknown since_id".to_string(),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ).into_response(),
+            };
+            return Ok(reply);
+        },
+        _ => return Err(warp::reject::not_found()),
+    };
+    Ok(warp::reply::with_header(r.unwrap(), "content-type", "application/json").into_response())
+}
+
+async fn serve_sedes(runs: Arc<Mutex<DB>>) -> Result<impl warp::Reply, warp::Rejection> {
     let db = runs.lock().await;
-    let r = serde_json::to_string(&db.get_scoreboard()).unwrap();
+    let r = serde_json::to_string(&db.config.contest).unwrap();
     Ok(r)
 }
 
@@ -216,9 +591,9 @@ pub fn random_path_part() -> String {
         .collect()
 }
 
-pub async fn serve_simple_contest(url_base : String, server_port : u16, secret : &String) {
+pub async fn serve_simple_contest(source : Box<dyn ContestSource>, poll : PollConfig, server_port : u16, secret : &String, config : ContestConfig) {
 
-    let shared_db = spawn_db_update(url_base);
+    let shared_db = spawn_db_update(source, config, poll);
     serve_simple_contest_assets(shared_db, server_port, secret).await
 }
 
@@ -0,0 +1,42 @@
+use std::env;
+
+use lib_server::*;
+use lib_server::dataio::*;
+use maratona_animeitor_rust::qualification::QualificationReason;
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Expected 2 arguments: {:?}", args);
+        return;
+    }
+    let url_base = args[1].clone();
+    let config = match read_config(&args[2]) {
+        Ok(c) => c,
+        Err(e) => panic!("Could not read config file {}: {}", args[2], e),
+    };
+
+    let (time_data, contest_data, runs_data) = match read_snapshot(&url_base).await {
+        Ok(w) => w,
+        Err(e) => panic!("Could not read webcast {}: {}", url_base, e),
+    };
+
+    let mut db = DB::with_config(config);
+    if let Err(e) = db.refresh_db(time_data, contest_data, runs_data) {
+        panic!("Could not compute the scoreboard: {}", e);
+    }
+
+    let qualified = match db.qualified() {
+        Ok(q) => q,
+        Err(e) => panic!("Could not compute the qualification: {}", e),
+    };
+
+    for (i, q) in qualified.iter().enumerate() {
+        let reason = match &q.reason {
+            QualificationReason::Sede { sede, placement } => format!("{}º em {}", placement, sede),
+            QualificationReason::Wildcard { placement } => format!("vaga extra, {}º", placement),
+        };
+        println!("{:3} {:15} {:10} {:40} {}", i + 1, q.team_login, q.escola, q.name, reason);
+    }
+}
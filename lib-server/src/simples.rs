@@ -2,12 +2,40 @@ use std::env;
 use tokio;
 
 use lib_server::*;
+use lib_server::dataio::read_config;
+use lib_server::source::*;
+use maratona_animeitor_rust::configdata::ContestConfig;
+
+const USAGE: &str = "Options: --archive <dir> to record the webcast, --replay <speed> to replay a recorded directory";
+
+/// Removes `--name value` from the arguments, returning the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let i = match args.iter().position(|a| a == name) {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    if i + 1 >= args.len() {
+        return Err(format!("Missing the value of {}", name));
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
 
 #[tokio::main]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        eprintln!("Expected 2 arguments: {:?}", args);
+    let mut args: Vec<String> = env::args().collect();
+    let (archive, replay) = match (take_option(&mut args, "--archive"), take_option(&mut args, "--replay")) {
+        (Ok(archive), Ok(replay)) => (archive, replay),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            return;
+        }
+    };
+    if args.len() != 3 && args.len() != 4 {
+        eprintln!("Expected 2 or 3 arguments: {:?}", args);
+        eprintln!("{}", USAGE);
         return;
     }
     let server_port :u16= match args[1].parse() {
@@ -15,6 +43,39 @@ async fn main() {
         Err(e) => panic!("Could not parse port {}", e),
     };
     let url_base = args[2].clone();
+    let config = match args.get(3) {
+        None => ContestConfig::default(),
+        Some(path) => match read_config(path) {
+            Ok(c) => c,
+            Err(e) => panic!("Could not read config file {}: {}", path, e),
+        },
+    };
+
+    let (source, poll): (Box<dyn ContestSource>, PollConfig) = match (replay, archive) {
+        (Some(speed), _) => {
+            let speed = match speed.parse() {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("Could not parse replay speed {}: {}", speed, e);
+                    eprintln!("{}", USAGE);
+                    return;
+                }
+            };
+            match ReplaySource::new(&url_base, speed) {
+                // the snapshots are local, so the replay can follow its clock closely
+                Ok(r) => (Box::new(r), PollConfig { interval: std::time::Duration::from_secs(1), ..PollConfig::default() }),
+                Err(e) => {
+                    eprintln!("Could not replay {}: {}", url_base, e);
+                    return;
+                }
+            }
+        },
+        (None, Some(archive)) => (
+            Box::new(RecordingSource { path: url_base, archive: archive.into() }),
+            PollConfig::default(),
+        ),
+        (None, None) => (source_from_path(&url_base), PollConfig::default()),
+    };
 
     let secret = random_path_part();
 
@@ -46,7 +107,7 @@ async fn main() {
     );
     
     
-    serve_simple_contest(url_base, server_port, &secret).await;
+    serve_simple_contest(source, poll, server_port, &secret, config).await;
 
 }
 
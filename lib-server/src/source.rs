@@ -0,0 +1,413 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use maratona_animeitor_rust::data::{ContestFile, RunsFile, Seconds, TimeFile};
+
+use crate::dataio::{read_contest, read_runs, ContestIOError};
+
+/// The time, contest and runs read from a source.
+pub type Snapshot = (TimeFile, ContestFile, RunsFile);
+
+pub type SnapshotFuture<'a> = Pin<Box<dyn Future<Output = Result<Snapshot, ContestIOError>> + Send + 'a>>;
+
+/// Where the server reads the contest from, polled by `spawn_db_update`.
+pub trait ContestSource: Send {
+    /// Reads the current state of the contest.
+    fn fetch(&mut self) -> SnapshotFuture<'_>;
+
+    /// Shown in the logs.
+    fn name(&self) -> String;
+}
+
+/// A webcast zip or a CLICS event feed, from an url or a local file.
+pub struct PathSource {
+    pub path: String,
+}
+
+impl ContestSource for PathSource {
+    fn fetch(&mut self) -> SnapshotFuture<'_> {
+        Box::pin(crate::read_snapshot(&self.path))
+    }
+
+    fn name(&self) -> String {
+        self.path.clone()
+    }
+}
+
+/// An unpacked webcast, with the `time`, `contest` and `runs` files.
+pub struct DirectorySource {
+    pub dir: PathBuf,
+}
+
+fn read_directory(dir: &Path) -> Result<Snapshot, ContestIOError> {
+    let read = |name: &str| std::fs::read_to_string(dir.join(name));
+    let time = read("time")?.trim().parse()?;
+    let contest = read_contest(&read("contest")?)?;
+    let runs = read_runs(&read("runs")?)?;
+    Ok((time, contest, runs))
+}
+
+/// Runs a blocking read outside of the server threads.
+async fn read_blocking<F>(read: F) -> Result<Snapshot, ContestIOError>
+where
+    F: FnOnce() -> Result<Snapshot, ContestIOError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(read)
+        .await
+        .map_err(|e| ContestIOError::Info(format!("Error reading the files: {}", e)))?
+}
+
+impl ContestSource for DirectorySource {
+    fn fetch(&mut self) -> SnapshotFuture<'_> {
+        let dir = self.dir.clone();
+        Box::pin(read_blocking(move || read_directory(&dir)))
+    }
+
+    fn name(&self) -> String {
+        self.dir.display().to_string()
+    }
+}
+
+/// A directory for an unpacked webcast, otherwise a zip or a feed.
+pub fn source_from_path(path: &str) -> Box<dyn ContestSource> {
+    if Path::new(path).is_dir() {
+        Box::new(DirectorySource { dir: PathBuf::from(path) })
+    } else {
+        Box::new(PathSource { path: path.to_string() })
+    }
+}
+
+/// Reads like `PathSource`, and also stores everything it reads in `archive`,
+/// named by the time it was read, so it can be replayed.
+pub struct RecordingSource {
+    pub path: String,
+    pub archive: PathBuf,
+}
+
+impl RecordingSource {
+    fn store(&self, data: &[u8]) -> Result<(), ContestIOError> {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let extension = if data.starts_with(b"PK") { "zip" } else { "ndjson" };
+        std::fs::create_dir_all(&self.archive)?;
+        std::fs::write(self.archive.join(format!("webcast_{}.{}", millis, extension)), data)?;
+        Ok(())
+    }
+}
+
+impl ContestSource for RecordingSource {
+    fn fetch(&mut self) -> SnapshotFuture<'_> {
+        Box::pin(async move {
+            let data = crate::read_bytes_from_path(&self.path).await?;
+            if let Err(e) = self.store(&data) {
+                eprintln!("Could not record {}: {}", self.path, e);
+            }
+            crate::read_snapshot_data(data, crate::live_clock(&self.path))
+        })
+    }
+
+    fn name(&self) -> String {
+        self.path.clone()
+    }
+}
+
+/// The number in the name of a recorded file, such as `webcast_1605470000000.zip` or `arquivo_12.zip`.
+fn recording_number(path: &Path) -> Option<u128> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits: String = stem.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// When a file recorded by `RecordingSource`, named `webcast_{millis}`, was read.
+fn recorded_at(path: &Path) -> Option<time::OffsetDateTime> {
+    let millis: i64 = path.file_stem()?.to_str()?.strip_prefix("webcast_")?.parse().ok()?;
+    Some(time::OffsetDateTime::from_unix_timestamp(millis / 1000) + time::Duration::milliseconds(millis % 1000))
+}
+
+/// Replays the snapshots recorded in a directory, in the order of the numbers in their names.
+/// The contest time of the replay starts at the time of the first snapshot, and runs `speed`
+/// times faster than the real clock; each fetch gives the latest snapshot already reached.
+pub struct ReplaySource {
+    pub dir: PathBuf,
+    /// Shared with the blocking task that reads the files.
+    replay: Arc<Mutex<Replay>>,
+}
+
+struct Replay {
+    dir: PathBuf,
+    speed: f64,
+    files: Vec<PathBuf>,
+    /// Index of the next file to read.
+    next: usize,
+    /// The next snapshot, already read.
+    upcoming: Option<Snapshot>,
+    current: Option<Snapshot>,
+    /// When the replay started, and the contest time it started at.
+    clock: Option<(Instant, TimeFile)>,
+}
+
+impl ReplaySource {
+    pub fn new(dir: &str, speed: f64) -> Result<Self, ContestIOError> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(ContestIOError::Info(format!("The replay speed must be positive, not {}", speed)));
+        }
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("zip") | Some("ndjson")))
+            .filter(|p| recording_number(p).is_some())
+            .collect();
+        if files.is_empty() {
+            return Err(ContestIOError::Info(format!("No recorded snapshots in {}", dir)));
+        }
+        files.sort_by_key(|p| recording_number(p));
+        let replay = Replay {
+            dir: PathBuf::from(dir),
+            speed,
+            files,
+            next: 0,
+            upcoming: None,
+            current: None,
+            clock: None,
+        };
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            replay: Arc::new(Mutex::new(replay)),
+        })
+    }
+}
+
+impl Replay {
+    /// Reads the next file, skipping the ones that can not be read.
+    fn read_upcoming(&mut self) -> Option<&Snapshot> {
+        while self.upcoming.is_none() && self.next < self.files.len() {
+            let path = &self.files[self.next];
+            self.next += 1;
+            match std::fs::read(path).map_err(ContestIOError::from).and_then(|data| crate::read_snapshot_data(data, recorded_at(path))) {
+                Ok(snapshot) => self.upcoming = Some(snapshot),
+                Err(e) => eprintln!("Skipping recorded snapshot {}: {}", path.display(), e),
+            }
+        }
+        self.upcoming.as_ref()
+    }
+
+    /// The contest time the replay is at.
+    fn virtual_time(&mut self) -> Option<TimeFile> {
+        if self.clock.is_none() {
+            let start = self.read_upcoming()?.0;
+            self.clock = Some((Instant::now(), start));
+        }
+        let (started, start) = self.clock?;
+        let elapsed = started.elapsed().as_secs_f64() * self.speed;
+        Some(Seconds(start.0 + elapsed as i64))
+    }
+
+    fn advance(&mut self) -> Result<Snapshot, ContestIOError> {
+        let now = self.virtual_time();
+        while let Some(time) = self.read_upcoming().map(|s| s.0) {
+            if self.current.is_some() && Some(time) > now {
+                break;
+            }
+            self.current = self.upcoming.take();
+        }
+        self.current
+            .clone()
+            .ok_or_else(|| ContestIOError::Info(format!("No readable snapshots in {}", self.dir.display())))
+    }
+}
+
+impl ContestSource for ReplaySource {
+    fn fetch(&mut self) -> SnapshotFuture<'_> {
+        let replay = self.replay.clone();
+        Box::pin(read_blocking(move || {
+            let mut replay = replay
+                .lock()
+                .map_err(|_| ContestIOError::Info("A previous replay read failed".to_string()))?;
+            replay.advance()
+        }))
+    }
+
+    fn name(&self) -> String {
+        format!("replay of {}", self.dir.display())
+    }
+}
+
+/// How often a source is polled.
+#[derive(Debug, Clone, Copy)]
+pub struct PollConfig {
+    pub interval: Duration,
+    /// Wait after the first failure, doubled at each failure in a row.
+    pub retry: Duration,
+    pub max_backoff: Duration,
+    /// Time without a successful update after which the data is reported as stale.
+    pub stale_after: Duration,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            retry: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(120),
+            stale_after: Duration::from_secs(120),
+        }
+    }
+}
+
+impl PollConfig {
+    /// The wait after `failures` failures in a row.
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let factor = 1u32 << failures.saturating_sub(1).min(16);
+        self.retry
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maratona_animeitor_rust::configdata::ContestConfig;
+
+    /// Fails a number of times before reading the directory.
+    struct Flaky {
+        failures: usize,
+        inner: DirectorySource,
+    }
+
+    impl ContestSource for Flaky {
+        fn fetch(&mut self) -> SnapshotFuture<'_> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Box::pin(async { Err(ContestIOError::Info("unavailable".to_string())) });
+            }
+            self.inner.fetch()
+        }
+
+        fn name(&self) -> String {
+            "flaky".to_string()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let poll = PollConfig::default();
+        assert_eq!(poll.backoff(1), Duration::from_secs(5));
+        assert_eq!(poll.backoff(2), Duration::from_secs(10));
+        assert_eq!(poll.backoff(3), Duration::from_secs(20));
+        assert_eq!(poll.backoff(6), Duration::from_secs(120));
+        assert_eq!(poll.backoff(100), Duration::from_secs(120));
+    }
+
+    #[tokio::test]
+    async fn reads_directory() -> Result<(), ContestIOError> {
+        let mut source = source_from_path("test/webcast_zip_1a_fase_2020");
+        let (_, contest, runs) = source.fetch().await?;
+        assert!(!contest.teams.is_empty());
+        assert_eq!(runs.len(), 6285);
+        assert!(source_from_path("test/missing").fetch().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn updater_retries_failed_sources() {
+        let source = Flaky {
+            failures: 3,
+            inner: DirectorySource { dir: PathBuf::from("test/webcast_zip_1a_fase_2020") },
+        };
+        let poll = PollConfig {
+            interval: Duration::from_secs(60),
+            retry: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(20),
+            stale_after: Duration::from_millis(15),
+        };
+        let db = crate::spawn_db_update(Box::new(source), ContestConfig::default(), poll);
+        for _ in 0..200 {
+            if db.lock().await.run_file.len() > 0 {
+                return;
+            }
+            tokio::time::delay_for(Duration::from_millis(50)).await;
+        }
+        panic!("the updater did not retry");
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("animeitor_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn records_what_it_reads() -> Result<(), ContestIOError> {
+        let archive = temp_dir("archive");
+        let mut source = RecordingSource {
+            path: "test/webcast_zip_1a_fase_2020.zip".to_string(),
+            archive: archive.clone(),
+        };
+        let (time, _, runs) = source.fetch().await?;
+        tokio::time::delay_for(Duration::from_millis(2)).await;
+        source.fetch().await?;
+
+        let recorded: Vec<_> = std::fs::read_dir(&archive)?.collect();
+        assert_eq!(recorded.len(), 2);
+        let (replayed_time, _, replayed_runs) = ReplaySource::new(archive.to_str().unwrap(), 1.0)?.fetch().await?;
+        assert_eq!(replayed_time, time);
+        assert_eq!(replayed_runs.sorted(), runs.sorted());
+        std::fs::remove_dir_all(&archive)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replays_in_order_of_the_recording() -> Result<(), ContestIOError> {
+        let dir = temp_dir("replay");
+        std::fs::create_dir_all(&dir)?;
+        for n in &[1, 500, 1000] {
+            let name = format!("arquivo_{}.zip", n);
+            std::fs::copy(Path::new("test/webcast_zip_aquecimento_1a_fase_2020").join(&name), dir.join(&name))?;
+        }
+        std::fs::write(dir.join("baixarzips.rb"), "")?;
+
+        // one second of contest each thousand years
+        let mut slow = ReplaySource::new(dir.to_str().unwrap(), 1e-10)?;
+        assert_eq!(slow.fetch().await?.0, Seconds(1203));
+        assert_eq!(slow.fetch().await?.0, Seconds(1203));
+
+        // one hour of contest for each millisecond
+        let mut fast = ReplaySource::new(dir.to_str().unwrap(), 3_600_000.0)?;
+        assert_eq!(fast.fetch().await?.0, Seconds(1203));
+        tokio::time::delay_for(Duration::from_millis(2)).await;
+        assert_eq!(fast.fetch().await?.0, Seconds(10800));
+
+        assert!(ReplaySource::new("test/webcast_zip_1a_fase_2020", 1.0).is_err());
+        for speed in &[0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(ReplaySource::new(dir.to_str().unwrap(), *speed).is_err());
+        }
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_feeds_at_the_time_they_were_recorded() -> Result<(), ContestIOError> {
+        let dir = temp_dir("replay_feed");
+        std::fs::create_dir_all(&dir)?;
+        let feed = std::fs::read_to_string("test/clics_event_feed.ndjson")?;
+        let running = feed.lines().take(31).collect::<Vec<_>>().join("\n");
+        // 2021-03-27T13:00:00Z, the start of the contest
+        let start: i64 = 1_616_850_000_000;
+        std::fs::write(dir.join(format!("webcast_{}.ndjson", start + 3_600_000)), running)?;
+        std::fs::write(dir.join(format!("webcast_{}.ndjson", start + 6 * 3_600_000)), feed)?;
+
+        let mut replay = ReplaySource::new(dir.to_str().unwrap(), 1.0)?;
+        assert_eq!(replay.fetch().await?.0, Seconds(3600));
+        assert_eq!(replay.fetch().await?.0, Seconds(3600));
+
+        let mut fast = ReplaySource::new(dir.to_str().unwrap(), 3_600_000.0)?;
+        assert_eq!(fast.fetch().await?.0, Seconds(3600));
+        tokio::time::delay_for(Duration::from_millis(5)).await;
+        assert_eq!(fast.fetch().await?.0, Seconds(300 * 60));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
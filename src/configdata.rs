@@ -1,3 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::data::{AwardRules, Awards, ContestFile, FirstSolve, Minutes, ProblemInfo, ScoringRules, Team};
+use crate::qualification::QualificationRules;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A code of a sede that matches no team.
+    UnmatchedCode(String, String),
+    /// A code used by two sibling sedes.
+    DuplicateCode(String, String, String),
+    /// Every error found when validating the config.
+    Invalid(Vec<ConfigError>),
+}
+
+impl std::error::Error for ConfigError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ConfigError: {:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sede {
     pub name: String,
     pub source: String,
@@ -17,15 +44,381 @@ impl Sede {
             codes : codes.iter().map(|c| c.to_string()).collect(),
         }
     }
+
+    /// Whether the team login starts with one of the codes of this sede.
+    pub fn contains_login(&self, login: &str) -> bool {
+        self.codes.iter().any(|c| login.starts_with(c.as_str()))
+    }
+
+    /// Whether this is a supersede of `other`, with all of its codes and more.
+    fn groups(&self, other: &Sede) -> bool {
+        self.codes.len() > other.codes.len() && other.codes.iter().all(|c| self.codes.contains(c))
+    }
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Contest {
+    #[serde(default)]
     pub sedes : Vec<Sede>,
 }
 
+/// The position of a team inside a sede, and in the whole contest.
+/// Unofficial teams have both placements zero.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RankingEntry {
+    pub team_login: String,
+    pub local_placement: usize,
+    pub global_placement: usize,
+}
+
+/// The teams of a sede, in scoreboard order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SedeRanking {
+    pub sede: Sede,
+    pub entries: Vec<RankingEntry>,
+    /// First accepted run of each problem inside the sede, by label.
+    pub first_solved: BTreeMap<String, FirstSolve>,
+}
+
+impl SedeRanking {
+    pub fn contains_login(&self, login: &str) -> bool {
+        self.entries.iter().any(|e| e.team_login == login)
+    }
+}
+
 impl Contest {
     pub fn new(sedes:Vec<Sede>) -> Self {
         Self { sedes }
     }
+
+    pub fn sede(&self, source: &str) -> Option<&Sede> {
+        self.sedes.iter().find(|s| s.source == source)
+    }
+
+    /// Whether the team belongs to the sede, either by one of its codes
+    /// or by any of the sedes below it in the hierarchy.
+    pub fn sede_contains_login(&self, sede: &Sede, login: &str) -> bool {
+        self.contains_login_below(sede, login, self.sedes.len())
+    }
+
+    /// The most specific sede of the team: the one with the longest code matching its login,
+    /// and among those the one with the fewest codes, so supersedes lose to their sedes.
+    pub fn sede_of_login(&self, login: &str) -> Option<&Sede> {
+        self.sedes.iter()
+            .filter_map(|s| {
+                let len = s.codes.iter().filter(|c| login.starts_with(c.as_str())).map(|c| c.len()).max()?;
+                Some(((len, std::cmp::Reverse(s.codes.len())), s))
+            })
+            .max_by_key(|(key, _)| *key)
+            .map(|(_, s)| s)
+    }
+
+    fn contains_login_below(&self, sede: &Sede, login: &str, depth: usize) -> bool {
+        if sede.contains_login(login) {
+            return true;
+        }
+        depth > 0 && self.sedes.iter()
+            .filter(|child| child.parent_source == sede.source && child.source != sede.source)
+            .any(|child| self.contains_login_below(child, login, depth - 1))
+    }
+
+    /// Ranks the teams of the sede, using the placements already computed for the contest.
+    /// Teams tied in the contest stay tied in the sede.
+    pub fn ranking(&self, sede: &Sede, contest: &ContestFile) -> SedeRanking {
+        let teams: Vec<&Team> = contest.score_board.iter()
+            .filter_map(|login| contest.teams.get(login))
+            .filter(|t| self.sede_contains_login(sede, &t.login))
+            .collect();
+
+        let entries = teams.iter()
+            .map(|t| RankingEntry {
+                team_login: t.login.clone(),
+                local_placement: if t.official {
+                    1 + teams.iter()
+                        .filter(|o| o.official && o.placement < t.placement)
+                        .count()
+                } else {
+                    0
+                },
+                global_placement: t.placement,
+            })
+            .collect();
+
+        SedeRanking {
+            sede: sede.clone(),
+            entries,
+            first_solved: contest.first_solves(|t| self.sede_contains_login(sede, &t.login)),
+        }
+    }
+
+    /// Rankings of every sede, by source.
+    pub fn rankings(&self, contest: &ContestFile) -> BTreeMap<String, SedeRanking> {
+        self.sedes.iter()
+            .map(|sede| (sede.source.clone(), self.ranking(sede, contest)))
+            .collect()
+    }
+
+    /// Awards of the contest, including the champions of every sede.
+    pub fn awards(&self, contest: &ContestFile) -> Awards {
+        let mut awards = Awards::new(contest);
+        for (source, ranking) in self.rankings(contest) {
+            awards.add_sede_champions(
+                &source,
+                ranking.entries.iter().map(|e| (&e.team_login, e.local_placement)),
+            );
+        }
+        awards
+    }
+
+    /// Checks the sedes against the teams of the contest.
+    /// A code that matches no team, or that a sibling sede also uses, is an error,
+    /// and all of them are returned together.
+    /// A code that is a prefix of another code in a sibling sede is reported as a warning.
+    pub fn validate(&self, contest: &ContestFile) -> Result<Vec<String>, ConfigError> {
+        let mut errors = Vec::new();
+        for sede in &self.sedes {
+            for code in &sede.codes {
+                if !contest.teams.keys().any(|login| login.starts_with(code.as_str())) {
+                    errors.push(ConfigError::UnmatchedCode(sede.source.clone(), code.clone()));
+                }
+            }
+        }
+        let (duplicates, warnings) = self.check_codes();
+        errors.extend(duplicates);
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+
+    /// Checks the codes of sibling sedes against each other, which needs no team.
+    /// Run when the config is read, so a broken hierarchy is caught before the first update.
+    pub fn validate_codes(&self) -> Result<Vec<String>, ConfigError> {
+        let (errors, warnings) = self.check_codes();
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+
+    /// Codes repeated by sibling sedes, and warnings for codes that are prefixes of sibling codes.
+    fn check_codes(&self) -> (Vec<ConfigError>, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        for a in &self.sedes {
+            for b in &self.sedes {
+                if a.source == b.source || a.parent_source != b.parent_source {
+                    continue;
+                }
+                for code_a in &a.codes {
+                    for code_b in &b.codes {
+                        if code_a == code_b {
+                            // Each pair of sedes is seen twice, and a supersede repeats
+                            // the codes of the sedes it groups.
+                            if a.source < b.source && !a.groups(b) && !b.groups(a) {
+                                errors.push(ConfigError::DuplicateCode(code_a.clone(), a.source.clone(), b.source.clone()));
+                            }
+                        } else if code_b.starts_with(code_a.as_str()) {
+                            warnings.push(format!(
+                                "code {} of sede {} overlaps code {} of sede {}",
+                                code_a, a.source, code_b, b.source
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        (errors, warnings)
+    }
+}
+
+
+/// Team metadata that the data source does not provide. Only the given fields are replaced.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TeamInfo {
+    pub escola: Option<String>,
+    pub name: Option<String>,
+    pub institution: Option<String>,
+    pub region: Option<String>,
+    pub country: Option<String>,
+    pub categories: Option<Vec<String>>,
+    pub logo: Option<String>,
+    pub official: Option<bool>,
+}
+
+impl TeamInfo {
+    pub fn apply(&self, team: &mut Team) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(v) = value {
+                *field = v.clone();
+            }
+        }
+        set(&mut team.escola, &self.escola);
+        set(&mut team.name, &self.name);
+        set(&mut team.institution, &self.institution);
+        set(&mut team.region, &self.region);
+        set(&mut team.country, &self.country);
+        set(&mut team.categories, &self.categories);
+        set(&mut team.official, &self.official);
+        if self.logo.is_some() {
+            team.logo = self.logo.clone();
+        }
+    }
+}
+
+/// Contest settings read at runtime, complementing what the data source provides.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContestConfig {
+    /// Replaces the problems of the contest, when not empty.
+    #[serde(default)]
+    pub problems: Vec<ProblemInfo>,
+    /// Team metadata, by login.
+    #[serde(default)]
+    pub teams: BTreeMap<String, TeamInfo>,
+    /// The sede hierarchy, read from the `sedes` list.
+    #[serde(flatten)]
+    pub contest: Contest,
+    /// Rules to advance teams to the next phase.
+    #[serde(default)]
+    pub qualification: QualificationRules,
+    /// Replaces the medal counts of the contest.
+    #[serde(default)]
+    pub awards: Option<AwardRules>,
+    /// Replaces the scoring rules of the contest, including its penalty.
+    #[serde(default)]
+    pub scoring_rules: Option<ScoringRules>,
+    /// Replaces the time from which verdicts are hidden.
+    #[serde(default)]
+    pub answer_freeze_time: Option<Minutes>,
+    /// Time each verdict waits before being public.
+    #[serde(default)]
+    pub verdict_delay: Option<Minutes>,
+    /// Id of the contest in the Contest API, `contest` when not given.
+    #[serde(default)]
+    pub contest_id: Option<String>,
+    /// Start of the contest, in RFC 3339, for the absolute times of the Contest API.
+    #[serde(default)]
+    pub start_time: Option<String>,
+}
+
+impl ContestConfig {
+    pub fn apply(&self, contest: &mut ContestFile) {
+        if !self.problems.is_empty() {
+            contest.problems = self.problems.clone();
+        }
+        if let Some(rules) = &self.awards {
+            contest.award_rules = rules.clone();
+        }
+        if let Some(rules) = &self.scoring_rules {
+            contest.scoring_rules = rules.clone();
+        }
+        if self.answer_freeze_time.is_some() {
+            contest.answer_freeze_time = self.answer_freeze_time;
+        }
+        if let Some(delay) = self.verdict_delay {
+            contest.verdict_delay = delay;
+        }
+        for team in contest.teams.values_mut() {
+            if team.region.is_empty() {
+                if let Some(sede) = self.contest.sede_of_login(&team.login) {
+                    team.region = sede.name.clone();
+                }
+            }
+        }
+        for (login, info) in &self.teams {
+            if let Some(team) = contest.teams.get_mut(login) {
+                info.apply(team);
+            }
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Answer, Medal, RunTuple};
+
+    /// Teams of Alagoas and Sergipe, grouped in the Nordeste supersede.
+    fn contest() -> (Contest, ContestFile) {
+        let sedes = Contest::new(vec![
+            Sede::new("Alagoas", "al", "Nordeste", "teambral"),
+            Sede::new("Sergipe", "se", "Nordeste", "teambrse"),
+            Sede::supersede("Nordeste", "Nordeste", "Brasil", vec![]),
+        ]);
+        let teams = ["teambral01", "teambral02", "teambrse01", "teambrsp01"]
+            .iter()
+            .map(|l| Team::new(l, "", ""))
+            .collect();
+        let mut contest = ContestFile::new(
+            "Teste".to_string(), teams, Minutes(0), Minutes(300), Minutes(240), ScoringRules::default(), ProblemInfo::numbered(2));
+        let runs = [(1, 10, "teambrsp01", "A"), (2, 20, "teambrse01", "A"), (3, 30, "teambral02", "A"), (4, 40, "teambral02", "B")];
+        for (id, time, team, prob) in runs.iter() {
+            contest.apply_run(&RunTuple {
+                id: *id, time: Minutes(*time), team_login: team.to_string(), prob: prob.to_string(), answer: Answer::Yes(*time),
+            }).unwrap();
+        }
+        contest.recalculate_placement().unwrap();
+        (sedes, contest)
+    }
+
+    #[test]
+    fn supersede_ranks_the_teams_of_its_sedes() {
+        let (sedes, contest) = contest();
+        let rankings = sedes.rankings(&contest);
+
+        let logins = |source: &str| -> Vec<(&str, usize, usize)> {
+            rankings[source].entries.iter()
+                .map(|e| (e.team_login.as_str(), e.local_placement, e.global_placement))
+                .collect()
+        };
+        assert_eq!(logins("al"), vec![("teambral02", 1, 1), ("teambral01", 2, 4)]);
+        assert_eq!(logins("Nordeste"), vec![("teambral02", 1, 1), ("teambrse01", 2, 3), ("teambral01", 3, 4)]);
+        assert_eq!(rankings["Nordeste"].first_solved["A"].team_login, "teambrse01");
+        assert_eq!(contest.first_solved["A"].team_login, "teambrsp01");
+
+        let awards = sedes.awards(&contest);
+        assert_eq!(awards.sede_champions["se"], vec!["teambrse01"]);
+        assert_eq!(awards.sede_champions["Nordeste"], vec!["teambral02"]);
+        assert_eq!(awards.medal("teambral02"), Some(Medal::Gold));
+    }
+
+    #[test]
+    fn region_comes_from_the_sede_unless_configured() {
+        let (sedes, mut contest) = contest();
+        let mut config = ContestConfig { contest: sedes, ..ContestConfig::default() };
+        config.contest.sedes.push(Sede::supersede("Nordeste", "Snordeste", "Brasil", vec!["teambral", "teambrse"]));
+        config.teams.insert("teambral02".to_string(), TeamInfo { region: Some("Maceió".to_string()), ..TeamInfo::default() });
+        config.apply(&mut contest);
+
+        assert_eq!(contest.teams["teambral01"].region, "Alagoas");
+        assert_eq!(contest.teams["teambrse01"].region, "Sergipe");
+        assert_eq!(contest.teams["teambral02"].region, "Maceió");
+        assert_eq!(contest.teams["teambrsp01"].region, "");
+    }
+
+    #[test]
+    fn validate_reports_unmatched_and_duplicated_codes() {
+        let (mut sedes, contest) = contest();
+        assert!(sedes.validate(&contest).unwrap().is_empty());
+
+        sedes.sedes.push(Sede::new("Marte", "Marte", "Brasil", "teambrxx"));
+        sedes.sedes.push(Sede::new("Alagoas de novo", "al2", "Nordeste", "teambral"));
+        match sedes.validate_codes() {
+            Err(ConfigError::Invalid(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert!(matches!(&errors[0], ConfigError::DuplicateCode(code, _, _) if code == "teambral"));
+            },
+            r => panic!("expected the duplicated code, got {:?}", r),
+        }
+        match sedes.validate(&contest) {
+            Err(ConfigError::Invalid(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(matches!(&errors[0], ConfigError::UnmatchedCode(sede, code) if sede == "Marte" && code == "teambrxx"));
+                assert!(matches!(&errors[1], ConfigError::DuplicateCode(code, a, b) if code == "teambral" && a == "al" && b == "al2"));
+            },
+            r => panic!("expected both errors, got {:?}", r),
+        }
+    }
+}
@@ -1,16 +1,64 @@
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BinaryHeap};
 use std::fmt;
+use std::num::ParseIntError;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
 // use itertools::Itertools;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Eq)]
 pub enum Answer {
     Yes(i64),
-    No,
+    No(Verdict),
     Wait,
     Unk,
 }
 
+/// Why a run was rejected.
+#[derive(Copy, Debug, PartialEq, Clone, Serialize, Deserialize, Eq)]
+pub enum Verdict {
+    /// Rejected, but the source did not say why.
+    Rejected,
+    WrongAnswer,
+    TimeLimitExceeded,
+    RuntimeError,
+    CompilationError,
+    MemoryLimitExceeded,
+    PresentationError,
+}
+
+impl Verdict {
+    /// Whether a run with this verdict counts as a try and costs penalty.
+    pub fn penalized(&self) -> bool {
+        *self != Verdict::CompilationError
+    }
+
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Verdict::Rejected => "NO",
+            Verdict::WrongAnswer => "WA",
+            Verdict::TimeLimitExceeded => "TLE",
+            Verdict::RuntimeError => "RTE",
+            Verdict::CompilationError => "CE",
+            Verdict::MemoryLimitExceeded => "MLE",
+            Verdict::PresentationError => "PE",
+        }
+    }
+
+    pub fn from_abbreviation(s: &str) -> Option<Self> {
+        match s {
+            "NO" => Some(Verdict::Rejected),
+            "WA" => Some(Verdict::WrongAnswer),
+            "TLE" => Some(Verdict::TimeLimitExceeded),
+            "RTE" => Some(Verdict::RuntimeError),
+            "CE" => Some(Verdict::CompilationError),
+            "MLE" => Some(Verdict::MemoryLimitExceeded),
+            "PE" => Some(Verdict::PresentationError),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ContestError {
     UnmatchedTeam(String),
@@ -30,33 +78,198 @@ impl fmt::Display for Answer {
     }
 }
 
-pub type TimeFile = i64;
+/// Contest time in seconds, as in the time file of the webcast and in the BOCA database.
+#[derive(Copy, Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(transparent)]
+pub struct Seconds(pub i64);
+
+/// Contest time in minutes, the unit of runs and of the scoreboard.
+/// Scores, such as solve times and penalties, are kept as plain minutes.
+#[derive(Copy, Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[serde(transparent)]
+pub struct Minutes(pub i64);
+
+impl Seconds {
+    /// Truncates to whole minutes; use `TimeRounding` when the rounding matters.
+    pub fn minutes(self) -> Minutes {
+        TimeRounding::Down.minutes(self)
+    }
+}
+
+impl From<Minutes> for Seconds {
+    fn from(m: Minutes) -> Self {
+        Seconds(m.0 * 60)
+    }
+}
+
+impl Add for Minutes {
+    type Output = Minutes;
+    fn add(self, other: Minutes) -> Minutes {
+        Minutes(self.0 + other.0)
+    }
+}
+
+impl Sub for Minutes {
+    type Output = Minutes;
+    fn sub(self, other: Minutes) -> Minutes {
+        Minutes(self.0 - other.0)
+    }
+}
+
+impl FromStr for Seconds {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Seconds(s.trim().parse()?))
+    }
+}
+
+impl FromStr for Minutes {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Minutes(s.trim().parse()?))
+    }
+}
+
+impl fmt::Display for Seconds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}s", self.0)
+    }
+}
+
+impl fmt::Display for Minutes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}min", self.0)
+    }
+}
+
+pub type TimeFile = Seconds;
+
+/// How a time measured in seconds is turned into contest minutes.
+#[derive(Copy, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum TimeRounding {
+    Down,
+    Nearest,
+    Up,
+}
+
+impl TimeRounding {
+    pub fn minutes(&self, seconds: Seconds) -> Minutes {
+        let seconds = seconds.0;
+        Minutes(match self {
+            TimeRounding::Down => seconds.div_euclid(60),
+            TimeRounding::Nearest => (seconds + 30).div_euclid(60),
+            TimeRounding::Up => (seconds + 59).div_euclid(60),
+        })
+    }
+}
+
+/// How placements are assigned to teams with exactly the same score.
+#[derive(Copy, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum RankingMode {
+    /// Every team gets its own placement, ties are broken by login.
+    Unique,
+    /// Tied teams share a placement, and the following placements are skipped.
+    Shared,
+}
+
+/// A criterion used to order the teams in the scoreboard.
+#[derive(Copy, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum TieBreaker {
+    /// More problems solved is better.
+    Solved,
+    /// Less penalty is better.
+    Penalty,
+    /// An earlier last accepted run is better.
+    LastSolveTime,
+    /// Compares the solve times one by one, from the earliest to the latest.
+    /// A team that runs out of solve times is worse.
+    SolveTimes,
+    /// An earlier first accepted run is better, and having none is worst.
+    FirstSolveTime,
+    /// Fewer submissions to the solved problems is better.
+    Submissions,
+}
+
+/// The rules used to turn the runs of a team into its score.
+/// Fields missing when deserializing keep their default values.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct ScoringRules {
+    pub penalty_per_wrong_answer: i64,
+    pub count_solve_time: bool,
+    pub time_rounding: TimeRounding,
+    pub ranking: RankingMode,
+    /// Criteria applied in order, the team login is always the last resort.
+    pub tie_breakers: Vec<TieBreaker>,
+}
+
+impl ScoringRules {
+    pub fn new(penalty_per_wrong_answer: i64) -> Self {
+        Self {
+            penalty_per_wrong_answer,
+            count_solve_time: true,
+            time_rounding: TimeRounding::Down,
+            ranking: RankingMode::Unique,
+            tie_breakers: vec![
+                TieBreaker::Solved,
+                TieBreaker::Penalty,
+                TieBreaker::LastSolveTime,
+            ],
+        }
+    }
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self::new(20)
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Problem {
     pub solved: bool,
     pub submissions: usize,
-    pub penalty: i64,
     pub time_solved: i64,
     pub answers: Vec<Answer>,
+    pub last_verdict: Option<Verdict>,
+    /// Id of the accepted run.
+    #[serde(default)]
+    pub id_solved: Option<i64>,
+    /// Ids of the runs in `answers`.
+    #[serde(default)]
+    answer_ids: Vec<i64>,
 }
 
 #[derive(Copy, Debug, Clone, Serialize, Deserialize)]
 pub struct TimerData {
     pub current_time: TimeFile,
-    pub score_freeze_time: i64,
+    pub score_freeze_time: Minutes,
+    pub maximum_time: Minutes,
 }
 
 impl TimerData {
-    pub fn new(current_time: TimeFile, score_freeze_time: i64) -> Self {
+    pub fn new(current_time: TimeFile, score_freeze_time: Minutes, maximum_time: Minutes) -> Self {
         Self {
             current_time,
             score_freeze_time,
+            maximum_time,
         }
     }
 
     pub fn is_frozen(&self) -> bool {
-        self.current_time >= self.score_freeze_time
+        self.current_time >= Seconds::from(self.score_freeze_time)
+    }
+
+    /// The clock `seconds` later, stopping at the end of the contest.
+    pub fn advance(&self, seconds: Seconds) -> Self {
+        let end = Seconds::from(self.maximum_time);
+        if self.current_time >= end {
+            return *self;
+        }
+        Self {
+            current_time: Seconds((self.current_time.0 + seconds.0).min(end.0)),
+            ..*self
+        }
     }
 }
 
@@ -66,11 +279,13 @@ impl Problem {
             solved: false,
             submissions: 0,
             time_solved: 0,
-            penalty: 0,
             answers: Vec::new(),
+            last_verdict: None,
+            id_solved: None,
+            answer_ids: Vec::new(),
         }
     }
-    fn add_run_problem(&mut self, answer: Answer) {
+    fn add_run_problem(&mut self, answer: Answer, id: i64) {
         if self.solved {
             return;
         }
@@ -78,17 +293,21 @@ impl Problem {
             Answer::Yes(tim) => {
                 self.solved = true;
                 self.submissions += 1;
-                self.penalty += tim;
                 self.time_solved = tim;
+                self.id_solved = Some(id);
                 self.answers.clear();
+                self.answer_ids.clear();
             }
-            Answer::No => {
-                self.submissions += 1;
-                self.penalty += 20;
+            Answer::No(verdict) => {
+                if verdict.penalized() {
+                    self.submissions += 1;
+                }
+                self.last_verdict = Some(verdict);
                 // self.answers.clear();
             }
             Answer::Wait => {
-                self.answers.push(Answer::No) // failsafe
+                self.answers.push(Answer::No(Verdict::Rejected)); // failsafe
+                self.answer_ids.push(id);
             }
             _ => {}
         }
@@ -98,56 +317,130 @@ impl Problem {
         !self.solved && self.answers.len() > 0
     }
 
-    fn add_run_frozen(&mut self, answer: Answer) {
+    /// Judged submissions and pending ones.
+    pub fn tries(&self) -> usize {
+        self.submissions + self.answers.len()
+    }
+
+    pub fn penalty(&self, rules: &ScoringRules) -> i64 {
+        if !self.solved {
+            return 0;
+        }
+        let time = if rules.count_solve_time { self.time_solved } else { 0 };
+        time + (self.submissions as i64 - 1) * rules.penalty_per_wrong_answer
+    }
+
+    fn add_run_frozen(&mut self, answer: Answer, id: i64) {
         if answer != Answer::Wait {
-            self.answers.push(answer)
+            self.answers.push(answer);
+            self.answer_ids.push(id);
         }
     }
 
     fn reveal_run_frozen(&mut self) {
         if self.wait() {
             let a = self.answers.remove(0);
-            self.add_run_problem(a);
+            let id = self.answer_ids.remove(0);
+            self.add_run_problem(a, id);
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Team {
     pub login: String,
+    /// Short name of the institution.
     pub escola: String,
     pub name: String,
+    /// Full name of the institution.
+    pub institution: String,
+    pub region: String,
+    pub country: String,
+    pub categories: Vec<String>,
+    /// Name of the logo file in `static/assets/logos`, without the extension.
+    pub logo: Option<String>,
+    /// Unofficial teams are shown in the scoreboard, but get no placement.
+    pub official: bool,
+    /// Zero for unofficial teams.
     pub placement: usize,
     pub problems: BTreeMap<String, Problem>,
 }
 
 use std::cmp::{Eq, Ord, Ordering};
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug)]
 pub struct Score {
     pub solved: usize,
     pub penalty: i64,
     pub max_solution_time: i64,
+    /// Times of the accepted runs, sorted from the earliest.
+    pub solve_times: Vec<i64>,
+    pub submissions: usize,
     pub team_login: String,
+    key: Vec<Vec<i64>>,
 }
 
+impl Score {
+    fn criterion(&self, tie_breaker: &TieBreaker) -> Vec<i64> {
+        match tie_breaker {
+            TieBreaker::Solved => vec![-(self.solved as i64)],
+            TieBreaker::Penalty => vec![self.penalty],
+            TieBreaker::LastSolveTime => vec![self.max_solution_time],
+            TieBreaker::SolveTimes => self
+                .solve_times
+                .iter()
+                .cloned()
+                .chain(std::iter::once(i64::MAX))
+                .collect(),
+            TieBreaker::FirstSolveTime => vec![self.solve_times.first().cloned().unwrap_or(i64::MAX)],
+            TieBreaker::Submissions => vec![self.submissions as i64],
+        }
+    }
+
+    /// Whether both scores are tied on everything but the team login.
+    pub fn same_rank(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+/// Scores are equal when they compare equal, that is, when they belong to the same team
+/// and are tied on every criterion.
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
 impl PartialOrd for Score {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(if self.solved != other.solved {
-            other.solved.cmp(&self.solved)
-        } else if self.penalty != other.penalty {
-            self.penalty.cmp(&other.penalty)
-        } else if self.max_solution_time != other.max_solution_time {
-            self.max_solution_time.cmp(&other.max_solution_time)
-        } else {
-            self.team_login.cmp(&other.team_login)
-        })
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Score {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        self.key
+            .cmp(&other.key)
+            .then_with(|| self.team_login.cmp(&other.team_login))
+    }
+}
+
+/// Scores of the teams by login, kept between placement calculations.
+/// Teams must be invalidated when their runs change.
+#[derive(Debug, Default)]
+pub struct ScoreCache {
+    scores: BTreeMap<String, Score>,
+}
+
+impl ScoreCache {
+    pub fn invalidate(&mut self, team_login: &str) {
+        self.scores.remove(team_login);
+    }
+
+    pub fn clear(&mut self) {
+        self.scores.clear();
     }
 }
 
@@ -157,11 +450,27 @@ impl Team {
             login: login.to_string(),
             escola: escola.to_string(),
             name: name.to_string(),
+            institution: escola.to_string(),
+            region: String::new(),
+            country: String::new(),
+            categories: Vec::new(),
+            logo: None,
+            official: true,
             placement: 0,
             problems: BTreeMap::new(),
         }
     }
 
+    pub fn logo_path(&self) -> Option<String> {
+        self.logo
+            .as_ref()
+            .map(|l| format!("/static/assets/logos/{}.png", l))
+    }
+
+    pub fn has_category(&self, category: &str) -> bool {
+        self.categories.iter().any(|c| c == category)
+    }
+
     pub fn dummy() -> Self {
         Self::new("<login>", "<escola>", "<nome>")
     }
@@ -170,14 +479,14 @@ impl Team {
         self.problems
             .entry(run.prob.clone())
             .or_insert(Problem::empty())
-            .add_run_problem(run.answer.clone());
+            .add_run_problem(run.answer.clone(), run.id);
     }
 
     fn apply_run_frozen(&mut self, run: &RunTuple) {
         self.problems
             .entry(run.prob.clone())
             .or_insert(Problem::empty())
-            .add_run_frozen(run.answer.clone());
+            .add_run_frozen(run.answer.clone(), run.id);
     }
 
     pub fn wait(&self) -> bool {
@@ -201,47 +510,265 @@ impl Team {
     //     self.problems.get(&run.prob).map(|p| !p.solved ).unwrap_or(true)
     // }
 
-    pub fn score(&self) -> Score {
+    pub fn score(&self, rules: &ScoringRules) -> Score {
         let mut solved = 0;
         let mut penalty = 0;
         let mut max_solution_time = 0;
+        let mut solve_times = Vec::new();
+        let mut submissions = 0;
         for (_, value) in self.problems.iter() {
             if value.solved {
                 solved += 1;
-                penalty += value.penalty;
+                penalty += value.penalty(rules);
                 max_solution_time = max_solution_time.max(value.time_solved);
+                solve_times.push(value.time_solved);
+                submissions += value.submissions;
             }
         }
-        Score {
+        solve_times.sort();
+
+        let mut score = Score {
             solved,
             penalty,
             max_solution_time,
+            solve_times,
+            submissions,
             team_login: self.login.clone(),
+            key: Vec::new(),
+        };
+        score.key = rules.tie_breakers.iter().map(|t| score.criterion(t)).collect();
+        score
+    }
+}
+
+/// Metadata of a problem, as shown in the scoreboard header.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ProblemInfo {
+    /// The label used by the runs, usually a letter.
+    pub label: String,
+    #[serde(default)]
+    pub name: String,
+    /// Balloon color, as a CSS color.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Hidden problems are not shown in the scoreboard.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+impl ProblemInfo {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            name: label.to_string(),
+            color: None,
+            hidden: false,
+        }
+    }
+
+    /// Problems labeled `A`, `B`, ..., `Z`, `AA`, `AB`, ...
+    pub fn numbered(number_problems: usize) -> Vec<Self> {
+        (0..number_problems)
+            .map(|i| Self::new(&Self::label_for(i)))
+            .collect()
+    }
+
+    fn label_for(i: usize) -> String {
+        let letter = ((b'A' + (i % 26) as u8) as char).to_string();
+        if i < 26 {
+            letter
+        } else {
+            Self::label_for(i / 26 - 1) + &letter
         }
     }
 }
 
+/// The first accepted run of a problem.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct FirstSolve {
+    pub team_login: String,
+    pub run_id: i64,
+    pub time: i64,
+}
+
+/// The placement of a team from `time` on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RankPoint {
+    pub time: Minutes,
+    pub placement: usize,
+}
+
+/// Placements of each team over the contest, by login.
+/// A point is recorded only when the placement changes.
+pub type RankHistory = BTreeMap<String, Vec<RankPoint>>;
+
+#[derive(Copy, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Medal {
+    Gold,
+    Silver,
+    Bronze,
+}
+
+impl Medal {
+    /// The CSS class used for the medal in the scoreboard.
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            Medal::Gold => "ouro",
+            Medal::Silver => "prata",
+            Medal::Bronze => "bronze",
+        }
+    }
+}
+
+/// How many medals of each kind are given, and who gets an honorable mention.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct AwardRules {
+    pub gold: usize,
+    pub silver: usize,
+    pub bronze: usize,
+    /// Tied teams always get the same medal. With this, a tie across a medal cutoff
+    /// gets the better medal, even if that exceeds the number of medals;
+    /// otherwise it gets the worse one, so a tie across the last bronze cutoff gets no medal
+    /// and fewer medals than configured are given.
+    #[serde(default)]
+    pub extend_ties: bool,
+    /// Teams without a medal that solved at least this many problems get an honorable mention.
+    #[serde(default = "AwardRules::default_honorable_mention")]
+    pub honorable_mention: usize,
+}
+
+impl AwardRules {
+    fn default_honorable_mention() -> usize {
+        1
+    }
+}
+
+impl Default for AwardRules {
+    fn default() -> Self {
+        Self {
+            gold: 3,
+            silver: 3,
+            bronze: 4,
+            extend_ties: false,
+            honorable_mention: Self::default_honorable_mention(),
+        }
+    }
+}
+
+/// Awards of a contest, computed from its scoreboard.
+/// Only official teams are awarded.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Awards {
+    /// Medal of each medalist, by login.
+    pub medals: BTreeMap<String, Medal>,
+    /// Team that solved each problem first, by problem label.
+    pub first_to_solve: BTreeMap<String, String>,
+    /// Teams in first place of each sede, by sede source.
+    pub sede_champions: BTreeMap<String, Vec<String>>,
+    pub honorable_mentions: Vec<String>,
+}
+
+impl Awards {
+    pub fn new(contest: &ContestFile) -> Self {
+        let rules = &contest.award_rules;
+        let mut awards = Self::default();
+
+        let official: Vec<(&Team, Score)> = contest
+            .score_board
+            .iter()
+            .filter_map(|login| contest.teams.get(login))
+            .filter(|t| t.official)
+            .map(|t| (t, t.score(&contest.scoring_rules)))
+            .collect();
+        // Ties are found from the scores, so they are the same in both ranking modes.
+        let mut first = 0;
+        while first < official.len() {
+            let tied = official[first..]
+                .iter()
+                .take_while(|(_, s)| s.same_rank(&official[first].1))
+                .count();
+            let rank = if rules.extend_ties { first + 1 } else { first + tied };
+            let medal = if rank <= rules.gold {
+                Some(Medal::Gold)
+            } else if rank <= rules.gold + rules.silver {
+                Some(Medal::Silver)
+            } else if rank <= rules.gold + rules.silver + rules.bronze {
+                Some(Medal::Bronze)
+            } else {
+                None
+            };
+            for (team, _) in &official[first..first + tied] {
+                let solved = team.problems.values().filter(|p| p.solved).count();
+                match medal {
+                    Some(m) => {
+                        awards.medals.insert(team.login.clone(), m);
+                    }
+                    None if solved >= rules.honorable_mention => {
+                        awards.honorable_mentions.push(team.login.clone())
+                    }
+                    None => (),
+                }
+            }
+            first += tied;
+        }
+
+        for (label, first) in contest.first_solves(|t| t.official) {
+            awards.first_to_solve.insert(label, first.team_login);
+        }
+        awards
+    }
+
+    /// Records the official teams in the first local placement of a sede.
+    pub fn add_sede_champions<'a, I>(&mut self, sede: &str, local_placements: I)
+    where
+        I: Iterator<Item = (&'a String, usize)>,
+    {
+        let champions: Vec<String> = local_placements
+            .filter(|(_, p)| *p == 1)
+            .map(|(login, _)| login.clone())
+            .collect();
+        if !champions.is_empty() {
+            self.sede_champions.insert(sede.to_string(), champions);
+        }
+    }
+
+    pub fn medal(&self, team_login: &str) -> Option<Medal> {
+        self.medals.get(team_login).cloned()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContestFile {
     pub contest_name: String,
     pub teams: BTreeMap<String, Team>,
-    pub current_time: i64,
-    pub maximum_time: i64,
-    pub score_freeze_time: i64,
-    pub penalty_per_wrong_answer: i64,
+    pub current_time: Minutes,
+    pub maximum_time: Minutes,
+    pub score_freeze_time: Minutes,
+    /// Runs submitted from this time on are shown as pending, even before the score freeze.
+    #[serde(default)]
+    pub answer_freeze_time: Option<Minutes>,
+    /// Time a verdict waits before being public, so rejudges can land first.
+    #[serde(default)]
+    pub verdict_delay: Minutes,
+    pub scoring_rules: ScoringRules,
     pub score_board: Vec<String>,
-    pub number_problems: usize,
+    pub problems: Vec<ProblemInfo>,
+    #[serde(default)]
+    pub award_rules: AwardRules,
+    /// First accepted run of each problem, by label.
+    #[serde(default)]
+    pub first_solved: BTreeMap<String, FirstSolve>,
 }
 
 impl ContestFile {
     pub fn new(
         contest_name: String,
         teams: Vec<Team>,
-        current_time: i64,
-        maximum_time: i64,
-        score_freeze_time: i64,
-        penalty: i64,
-        number_problems: usize,
+        current_time: Minutes,
+        maximum_time: Minutes,
+        score_freeze_time: Minutes,
+        scoring_rules: ScoringRules,
+        problems: Vec<ProblemInfo>,
     ) -> Self {
         let mut m = BTreeMap::new();
         for t in teams {
@@ -253,59 +780,224 @@ impl ContestFile {
             current_time,
             maximum_time,
             score_freeze_time,
-            penalty_per_wrong_answer: penalty,
+            answer_freeze_time: None,
+            verdict_delay: Minutes(0),
+            scoring_rules,
             score_board: Vec::new(),
-            number_problems: number_problems,
+            problems,
+            award_rules: AwardRules::default(),
+            first_solved: BTreeMap::new(),
         }
     }
 
+    /// Whether the public scoreboard is frozen: the freeze has started, and was not lifted
+    /// by moving it to the end of the contest.
+    pub fn is_frozen(&self) -> bool {
+        self.score_freeze_time <= self.current_time && self.score_freeze_time < self.maximum_time
+    }
+
+    /// Whether the verdict of a run submitted at `run_time` can be shown to the public.
+    pub fn is_verdict_public(&self, run_time: Minutes) -> bool {
+        run_time < self.score_freeze_time
+            && self.answer_freeze_time.map(|t| run_time < t).unwrap_or(true)
+            && run_time + self.verdict_delay <= self.current_time
+    }
+
+    pub fn visible_problems(&self) -> impl Iterator<Item = &ProblemInfo> {
+        self.problems.iter().filter(|p| !p.hidden)
+    }
+
+    /// The first accepted run of each problem, over the teams accepted by `filter`.
+    /// Runs accepted at the same time are ordered by their ids.
+    pub fn first_solves<F>(&self, filter: F) -> BTreeMap<String, FirstSolve>
+    where
+        F: Fn(&Team) -> bool,
+    {
+        let mut firsts: BTreeMap<String, FirstSolve> = BTreeMap::new();
+        for team in self.teams.values().filter(|t| filter(t)) {
+            for (label, problem) in &team.problems {
+                let run_id = match problem.id_solved {
+                    Some(id) if problem.solved => id,
+                    _ => continue,
+                };
+                let candidate = FirstSolve {
+                    team_login: team.login.clone(),
+                    run_id,
+                    time: problem.time_solved,
+                };
+                let better = firsts
+                    .get(label)
+                    .map(|f| (candidate.time, candidate.run_id) < (f.time, f.run_id))
+                    .unwrap_or(true);
+                if better {
+                    firsts.insert(label.clone(), candidate);
+                }
+            }
+        }
+        firsts
+    }
+
+    pub fn is_first_solve(&self, team_login: &str, problem: &str) -> bool {
+        self.first_solved
+            .get(problem)
+            .map(|f| f.team_login == team_login)
+            .unwrap_or(false)
+    }
+
     pub fn placement(&self, team_login: &String) -> Option<usize> {
         self.teams.get(team_login).map(|t| t.placement)
     }
 
+    /// Sorts the teams by score, keeping the order in `score_board` for display,
+    /// and assigns their placements according to the ranking mode.
+    /// Unofficial teams are skipped, and get placement zero.
     pub fn recalculate_placement(&mut self) -> Result<(), ContestError> {
-        let mut score_board = Vec::new();
-        for (key, _) in self.teams.iter() {
-            score_board.push(key.clone());
-        }
-        score_board.sort_by(|a, b| {
-            let score_a = self.teams.get(a).unwrap().score();
-            let score_b = self.teams.get(b).unwrap().score();
-            score_a.cmp(&score_b)
-        });
-        for (i, v) in score_board.iter().enumerate() {
-            match self.teams.get_mut(v) {
-                None => return Err(ContestError::UnmatchedTeam(v.clone())),
-                Some(t) => t.placement = i + 1,
+        self.recalculate_placement_with(&mut ScoreCache::default())
+    }
+
+    /// Like `recalculate_placement`, but only scores the teams missing from `cache`.
+    pub fn recalculate_placement_with(&mut self, cache: &mut ScoreCache) -> Result<(), ContestError> {
+        self.assign_placements(cache)?;
+        self.first_solved = self.first_solves(|_| true);
+        Ok(())
+    }
+
+    fn assign_placements(&mut self, cache: &mut ScoreCache) -> Result<(), ContestError> {
+        let rules = &self.scoring_rules;
+        let teams = &self.teams;
+        cache.scores.retain(|login, _| teams.contains_key(login));
+        for team in teams.values() {
+            cache
+                .scores
+                .entry(team.login.clone())
+                .or_insert_with(|| team.score(rules));
+        }
+        let mut scores: Vec<&Score> = cache.scores.values().collect();
+        scores.sort();
+
+        let shared = self.scoring_rules.ranking == RankingMode::Shared;
+        let mut ranked = 0;
+        let mut placement = 0;
+        let mut previous: Option<&Score> = None;
+        for score in scores.iter().cloned() {
+            let team = match self.teams.get_mut(&score.team_login) {
+                None => return Err(ContestError::UnmatchedTeam(score.team_login.clone())),
+                Some(t) => t,
+            };
+            if !team.official {
+                team.placement = 0;
+                continue;
+            }
+            ranked += 1;
+            if !(shared && previous.map(|p| p.same_rank(score)).unwrap_or(false)) {
+                placement = ranked;
             }
+            team.placement = placement;
+            previous = Some(score);
         }
 
+        self.score_board = scores.into_iter().map(|s| s.team_login.clone()).collect();
         Ok(())
     }
 
-    pub fn reload_score(&mut self) -> Result<(), ContestError> {
-        let mut score_board = Vec::new();
-        for (key, _) in self.teams.iter() {
-            score_board.push(key.clone());
-        }
-        score_board.sort_by(|a, b| {
-            let score_a = self.teams.get(a).unwrap().score();
-            let score_b = self.teams.get(b).unwrap().score();
-            score_a.cmp(&score_b)
-        });
-        for (i, v) in score_board.iter().enumerate() {
-            match self.teams.get_mut(v) {
-                None => return Err(ContestError::UnmatchedTeam(v.clone())),
-                Some(t) => t.placement = i + 1,
-            }
+    /// The contest with only the runs submitted before `time` applied on top of this one.
+    pub fn at_time(&self, runs: &RunsFile, time: Minutes) -> Result<Self, ContestError> {
+        let mut contest = self.clone();
+        for r in runs.sorted().iter().filter(|r| r.time < time) {
+            contest.apply_run(r)?;
+        }
+        contest.recalculate_placement()?;
+        Ok(contest)
+    }
+
+    /// Applies the runs on top of this contest, in order, recording every placement change.
+    /// Runs submitted at the same time are applied together.
+    pub fn rank_history(&self, runs: &RunsFile) -> Result<RankHistory, ContestError> {
+        let mut contest = self.clone();
+        let mut cache = ScoreCache::default();
+        contest.assign_placements(&mut cache)?;
+
+        let mut history: RankHistory = contest
+            .teams
+            .values()
+            .map(|t| {
+                let start = RankPoint { time: Minutes(0), placement: t.placement };
+                (t.login.clone(), vec![start])
+            })
+            .collect();
+
+        contest.apply_runs_with_history(runs.sorted(), &mut cache, &mut history)?;
+        Ok(history)
+    }
+
+    /// Updates `history`, built by `rank_history` from earlier versions of `runs`,
+    /// when no run before `since` changed. Only the placements from `since` on are recomputed.
+    pub fn update_rank_history(&self, runs: &RunsFile, since: Minutes, history: &mut RankHistory) -> Result<(), ContestError> {
+        if since <= Minutes(0) {
+            *history = self.rank_history(runs)?;
+            return Ok(());
         }
+        let runs = runs.sorted();
+        let first = runs.partition_point(|r| r.time < since);
 
-        self.score_board = score_board;
+        let mut contest = self.clone();
+        for r in &runs[..first] {
+            contest.apply_run(r)?;
+        }
+        let mut cache = ScoreCache::default();
+        contest.assign_placements(&mut cache)?;
+
+        for points in history.values_mut() {
+            points.retain(|p| p.time < since);
+        }
+        contest.apply_runs_with_history(&runs[first..], &mut cache, history)
+    }
+
+    /// Applies `runs`, sorted by time, updating the placements and extending `history`
+    /// with every placement change. `history` must end with the placements of this contest,
+    /// and the runs must be later than the ones it already covers.
+    pub fn apply_runs_with_history(
+        &mut self,
+        runs: &[RunTuple],
+        cache: &mut ScoreCache,
+        history: &mut RankHistory,
+    ) -> Result<(), ContestError> {
+        let mut i = 0;
+        while i < runs.len() {
+            let time = runs[i].time;
+            while i < runs.len() && runs[i].time == time {
+                self.apply_run(&runs[i])?;
+                cache.invalidate(&runs[i].team_login);
+                i += 1;
+            }
+            self.assign_placements(cache)?;
+
+            for team in self.teams.values() {
+                let points = history.entry(team.login.clone()).or_default();
+                if points.last().map(|p| p.placement) != Some(team.placement) {
+                    points.push(RankPoint { time, placement: team.placement });
+                }
+            }
+        }
+        self.assign_placements(cache)?;
+        self.first_solved = self.first_solves(|_| true);
         Ok(())
     }
 
+    pub fn reload_score(&mut self) -> Result<(), ContestError> {
+        self.recalculate_placement()
+    }
+
     pub fn dummy() -> Self {
-        Self::new("Dummy Contest".to_string(), Vec::new(), 0, 0, 0, 0, 0)
+        Self::new(
+            "Dummy Contest".to_string(),
+            Vec::new(),
+            Minutes(0),
+            Minutes(0),
+            Minutes(0),
+            ScoringRules::default(),
+            Vec::new(),
+        )
     }
 
     // pub fn useful_run(&self, r : &RunTuple) -> Result<bool, ContestError> {
@@ -338,7 +1030,7 @@ impl ContestFile {
             )),
             Some(t) => {
                 t.apply_run_frozen(&r);
-                Ok(t.score())
+                Ok(t.score(&self.scoring_rules))
             }
         }
     }
@@ -404,10 +1096,10 @@ impl Revelation {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct RunTuple {
     pub id: i64,
-    pub time: i64,
+    pub time: Minutes,
     pub team_login: String,
     pub prob: String,
     pub answer: Answer,
@@ -422,6 +1114,8 @@ pub struct RunsFile {
 pub struct RunsPanelItem {
     pub id: i64,
     pub placement: usize,
+    /// `FIRST_SOLVE` for the first accepted run of the problem,
+    /// `FIRST_SOLVE_IN_SEDE` for the first one inside the requested sede, zero otherwise.
     pub color: i64,
     pub escola: String,
     pub team_name: String,
@@ -430,6 +1124,11 @@ pub struct RunsPanelItem {
     pub result: Answer,
 }
 
+impl RunsPanelItem {
+    pub const FIRST_SOLVE: i64 = 1;
+    pub const FIRST_SOLVE_IN_SEDE: i64 = 2;
+}
+
 impl RunsFile {
     pub fn empty() -> Self {
         RunsFile { runs: Vec::new() }
@@ -453,7 +1152,25 @@ impl RunsFile {
         &self.runs
     }
 
-    pub fn filter_frozen(&self, frozen_time: i64) -> Self {
+    /// The runs as shown to the public: runs whose verdict is not public yet keep
+    /// their team, problem and time, but their verdict is replaced by `Wait`.
+    pub fn publish(&self, contest: &ContestFile) -> Self {
+        RunsFile {
+            runs: self
+                .runs
+                .iter()
+                .map(|r| {
+                    let mut r = r.clone();
+                    if !contest.is_verdict_public(r.time) {
+                        r.answer = Answer::Wait;
+                    }
+                    r
+                })
+                .collect(),
+        }
+    }
+
+    pub fn filter_frozen(&self, frozen_time: Minutes) -> Self {
         RunsFile {
             runs: self
                 .runs
@@ -483,7 +1200,7 @@ impl RunsQueue {
     pub fn setup_teams(&mut self, contest: &ContestFile) {
         for team in contest.teams.values() {
             if team.wait() {
-                self.queue.push(team.score())
+                self.queue.push(team.score(&contest.scoring_rules))
             }
         }
     }
@@ -497,7 +1214,7 @@ impl RunsQueue {
                 Some(team) => {
                     team.reveal_run_frozen();
                     if team.wait() {
-                        self.queue.push(team.score());
+                        self.queue.push(team.score(&contest.scoring_rules));
                     }
                 }
             },
@@ -521,21 +1238,335 @@ mod tests {
             //     Answer::Wait
             // }
             else {
-                Answer::No
+                Answer::No(Verdict::arbitrary(g))
             }
         }
     }
 
+    impl Arbitrary for Verdict {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            match g.next_u32() % 7 {
+                0 => Verdict::Rejected,
+                1 => Verdict::WrongAnswer,
+                2 => Verdict::TimeLimitExceeded,
+                3 => Verdict::RuntimeError,
+                4 => Verdict::CompilationError,
+                5 => Verdict::MemoryLimitExceeded,
+                _ => Verdict::PresentationError,
+            }
+        }
+    }
+
+    impl Arbitrary for TieBreaker {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            match g.next_u32() % 6 {
+                0 => TieBreaker::Solved,
+                1 => TieBreaker::Penalty,
+                2 => TieBreaker::LastSolveTime,
+                3 => TieBreaker::SolveTimes,
+                4 => TieBreaker::FirstSolveTime,
+                _ => TieBreaker::Submissions,
+            }
+        }
+    }
+
+    fn scores_from_runs(runs: &[(u8, u8, u16, bool)], tie_breakers: Vec<TieBreaker>) -> Vec<Score> {
+        let rules = ScoringRules {
+            tie_breakers,
+            ..ScoringRules::default()
+        };
+
+        let mut teams = BTreeMap::new();
+        for (team, prob, time, yes) in runs {
+            let login = format!("team{}", team % 8);
+            let answer = if *yes {
+                Answer::Yes(*time as i64)
+            } else {
+                Answer::No(Verdict::WrongAnswer)
+            };
+            teams
+                .entry(login.clone())
+                .or_insert_with(|| Team::new(&login, "", ""))
+                .apply_run(&RunTuple {
+                    id: 0,
+                    time: Minutes(*time as i64),
+                    team_login: login,
+                    prob: (prob % 4).to_string(),
+                    answer,
+                });
+        }
+        teams.values().map(|t| t.score(&rules)).collect()
+    }
+
+    fn contest_with(logins: &[&str], problems: usize) -> ContestFile {
+        let teams = logins.iter().map(|l| Team::new(l, "", "")).collect();
+        ContestFile::new(
+            "Teste".to_string(), teams, Minutes(0), Minutes(300), Minutes(240), ScoringRules::default(), ProblemInfo::numbered(problems))
+    }
+
+    fn accepted(id: i64, time: i64, team: &str, prob: &str) -> RunTuple {
+        RunTuple { id, time: Minutes(time), team_login: team.to_string(), prob: prob.to_string(), answer: Answer::Yes(time) }
+    }
+
+    #[test]
+    fn shared_placements_skip_after_ties() {
+        let mut contest = contest_with(&["teama", "teamb", "teamc", "teamd"], 2);
+        for r in &[accepted(1, 10, "teama", "A"), accepted(2, 20, "teama", "B"), accepted(3, 30, "teamb", "A"), accepted(4, 30, "teamc", "A")] {
+            contest.apply_run(r).unwrap();
+        }
+
+        contest.recalculate_placement().unwrap();
+        let placements = |c: &ContestFile| -> Vec<usize> { c.score_board.iter().map(|l| c.teams[l].placement).collect() };
+        assert_eq!(contest.score_board, vec!["teama", "teamb", "teamc", "teamd"]);
+        assert_eq!(placements(&contest), vec![1, 2, 3, 4]);
+
+        contest.scoring_rules.ranking = RankingMode::Shared;
+        contest.recalculate_placement().unwrap();
+        assert_eq!(contest.score_board, vec!["teama", "teamb", "teamc", "teamd"]);
+        assert_eq!(placements(&contest), vec![1, 2, 2, 4]);
+    }
+
+    #[test]
+    fn first_solve_ties_are_broken_by_run_id() {
+        let mut contest = contest_with(&["teama", "teamb"], 1);
+        for r in &[accepted(20, 10, "teamb", "A"), accepted(15, 10, "teama", "A")] {
+            contest.apply_run(r).unwrap();
+        }
+        contest.recalculate_placement().unwrap();
+        assert_eq!(contest.first_solved["A"].team_login, "teama");
+        assert!(contest.is_first_solve("teama", "A"));
+        assert!(!contest.is_first_solve("teamb", "A"));
+    }
+
+    #[test]
+    fn awards_skip_unofficial_teams() {
+        let mut contest = contest_with(&["guest", "teama", "teamb", "teamc"], 2);
+        contest.teams.get_mut("guest").unwrap().official = false;
+        contest.award_rules = AwardRules { gold: 1, silver: 0, bronze: 0, extend_ties: false, honorable_mention: 1 };
+        for r in &[accepted(1, 5, "guest", "A"), accepted(2, 10, "teama", "A"), accepted(3, 20, "teama", "B"), accepted(4, 30, "teamb", "A")] {
+            contest.apply_run(r).unwrap();
+        }
+        contest.recalculate_placement().unwrap();
+
+        let awards = Awards::new(&contest);
+        assert_eq!(awards.medal("teama"), Some(Medal::Gold));
+        assert_eq!(awards.medal("guest"), None);
+        assert_eq!(awards.honorable_mentions, vec!["teamb"]);
+        assert_eq!(awards.first_to_solve["A"], "teama");
+        assert_eq!(awards.first_to_solve["B"], "teama");
+        assert_eq!(contest.first_solved["A"].team_login, "guest");
+    }
+
+    #[test]
+    fn problem_labels_go_past_z() {
+        let labels: Vec<_> = ProblemInfo::numbered(28).into_iter().map(|p| p.label).collect();
+        assert_eq!(labels[0], "A");
+        assert_eq!(labels[25], "Z");
+        assert_eq!(labels[26], "AA");
+        assert_eq!(labels[27], "AB");
+    }
+
+    #[test]
+    fn timer_compares_seconds_with_the_freeze() {
+        let freeze = Minutes(240);
+        assert!(!TimerData::new(Seconds(240 * 60 - 1), freeze, Minutes(300)).is_frozen());
+        assert!(TimerData::new(Seconds(240 * 60), freeze, Minutes(300)).is_frozen());
+        assert_eq!(Seconds(599).minutes(), Minutes(9));
+        assert_eq!(TimeRounding::Up.minutes(Seconds(541)), Minutes(10));
+    }
+
+    #[test]
+    fn timer_advances_until_the_end() {
+        let timer = TimerData::new(Seconds(300 * 60 - 2), Minutes(240), Minutes(300));
+        assert_eq!(timer.advance(Seconds(1)).current_time, Seconds(300 * 60 - 1));
+        assert_eq!(timer.advance(Seconds(5)).current_time, Seconds(300 * 60));
+        let late = TimerData::new(Seconds(300 * 60 + 10), Minutes(240), Minutes(300));
+        assert_eq!(late.advance(Seconds(1)).current_time, late.current_time);
+    }
+
+    #[test]
+    fn medals_extend_to_ties() {
+        let teams = (0..4).map(|i| Team::new(&format!("team{:02}", i), "", "")).collect();
+        let mut contest = ContestFile::new(
+            "Teste".to_string(), teams, Minutes(0), Minutes(300), Minutes(240), ScoringRules::default(), ProblemInfo::numbered(3));
+        contest.award_rules = AwardRules { gold: 1, silver: 1, bronze: 1, extend_ties: false, honorable_mention: 1 };
+        let run = |id, team: &str, prob: &str| RunTuple {
+            id, time: Minutes(10), team_login: team.to_string(), prob: prob.to_string(), answer: Answer::Yes(10),
+        };
+        for r in &[run(1, "team00", "A"), run(2, "team00", "B"), run(3, "team01", "A"), run(4, "team02", "A")] {
+            contest.apply_run(r).unwrap();
+        }
+
+        for ranking in &[RankingMode::Unique, RankingMode::Shared] {
+            contest.scoring_rules.ranking = *ranking;
+            contest.award_rules.extend_ties = false;
+            contest.recalculate_placement().unwrap();
+            let awards = Awards::new(&contest);
+            assert_eq!(awards.medal("team00"), Some(Medal::Gold));
+            assert_eq!(awards.medal("team01"), Some(Medal::Bronze));
+            assert_eq!(awards.medal("team02"), Some(Medal::Bronze));
+            assert_eq!(awards.medal("team03"), None);
+            assert!(awards.honorable_mentions.is_empty());
+
+            contest.award_rules.extend_ties = true;
+            let awards = Awards::new(&contest);
+            assert_eq!(awards.medal("team01"), Some(Medal::Silver));
+            assert_eq!(awards.medal("team02"), Some(Medal::Silver));
+            assert_eq!(awards.medal("team03"), None);
+        }
+    }
+
+    #[test]
+    fn tie_across_the_last_cutoff_gets_no_medal_unless_extended() {
+        let teams = (0..4).map(|i| Team::new(&format!("team{:02}", i), "", "")).collect();
+        let mut contest = ContestFile::new(
+            "Teste".to_string(), teams, Minutes(0), Minutes(300), Minutes(240), ScoringRules::default(), ProblemInfo::numbered(3));
+        contest.award_rules = AwardRules { gold: 1, silver: 1, bronze: 1, extend_ties: false, honorable_mention: 1 };
+        let run = |id, team: &str, prob: &str| RunTuple {
+            id, time: Minutes(10), team_login: team.to_string(), prob: prob.to_string(), answer: Answer::Yes(10),
+        };
+        let runs = [
+            run(1, "team00", "A"), run(2, "team00", "B"), run(3, "team00", "C"),
+            run(4, "team01", "A"), run(5, "team01", "B"),
+            run(6, "team02", "A"), run(7, "team03", "A"),
+        ];
+        for r in &runs {
+            contest.apply_run(r).unwrap();
+        }
+        contest.recalculate_placement().unwrap();
+
+        // team02 and team03 are tied on the 3rd and 4th places, with 3 medals
+        let awards = Awards::new(&contest);
+        assert_eq!(awards.medal("team00"), Some(Medal::Gold));
+        assert_eq!(awards.medal("team01"), Some(Medal::Silver));
+        assert_eq!(awards.medal("team02"), None);
+        assert_eq!(awards.medal("team03"), None);
+        assert_eq!(awards.medals.len(), 2);
+        assert_eq!(awards.honorable_mentions, vec!["team02", "team03"]);
+
+        contest.award_rules.extend_ties = true;
+        let awards = Awards::new(&contest);
+        assert_eq!(awards.medal("team02"), Some(Medal::Bronze));
+        assert_eq!(awards.medal("team03"), Some(Medal::Bronze));
+        assert_eq!(awards.medals.len(), 4);
+    }
+
+    #[test]
+    fn rank_history_records_changes() {
+        let teams = vec![Team::new("teama", "A", "a"), Team::new("teamb", "B", "b")];
+        let rules = ScoringRules { ranking: RankingMode::Shared, ..ScoringRules::default() };
+        let contest = ContestFile::new(
+            "Teste".to_string(), teams, Minutes(0), Minutes(300), Minutes(240), rules, ProblemInfo::numbered(2));
+        let run = |id, time, team: &str, prob: &str| RunTuple {
+            id, time: Minutes(time), team_login: team.to_string(), prob: prob.to_string(), answer: Answer::Yes(time),
+        };
+        let runs = RunsFile::new(vec![
+            run(1, 10, "teamb", "A"),
+            run(2, 20, "teama", "A"),
+            run(3, 30, "teama", "B"),
+        ]);
+
+        let history = contest.rank_history(&runs).unwrap();
+        let placements = |login: &str| -> Vec<(i64, usize)> {
+            history[login].iter().map(|p| (p.time.0, p.placement)).collect()
+        };
+        assert_eq!(placements("teama"), vec![(0, 1), (10, 2), (30, 1)]);
+        assert_eq!(placements("teamb"), vec![(0, 1), (30, 2)]);
+    }
+
+    #[test]
+    fn rank_history_updates_from_the_changed_run() {
+        let contest = contest_with(&["teama", "teamb", "teamc"], 2);
+        let mut runs = vec![
+            accepted(1, 10, "teamb", "A"),
+            accepted(2, 20, "teama", "A"),
+            accepted(3, 30, "teama", "B"),
+            accepted(4, 40, "teamc", "A"),
+        ];
+        runs[1].answer = Answer::Wait;
+        let mut history = contest.rank_history(&RunsFile::new(runs.clone())).unwrap();
+
+        runs[1].answer = Answer::Yes(20);
+        let judged = RunsFile::new(runs);
+        contest.update_rank_history(&judged, Minutes(20), &mut history).unwrap();
+        assert_eq!(history, contest.rank_history(&judged).unwrap());
+    }
+
     quickcheck! {
+        fn score_order_is_total(runs : Vec<(u8, u8, u16, bool)>, tie_breakers : Vec<TieBreaker>) -> bool {
+            let scores = scores_from_runs(&runs, tie_breakers);
+            for a in &scores {
+                for b in &scores {
+                    if a.cmp(b) != b.cmp(a).reverse() {
+                        return false;
+                    }
+                    if (a.cmp(b) == Ordering::Equal) != (a.team_login == b.team_login) {
+                        return false;
+                    }
+                    if (a == b) != (a.cmp(b) == Ordering::Equal) {
+                        return false;
+                    }
+                    for c in &scores {
+                        if a <= b && b <= c && a > c {
+                            return false;
+                        }
+                    }
+                }
+            }
+            true
+        }
+
+        fn solve_times_compare_the_earliest_first(times : (u16, u16, u16, u16)) -> bool {
+            let mut t = [times.0, times.1, times.2, times.3];
+            t.sort();
+            // team1 solves at the earliest and the latest times, team2 in between,
+            // and team3 solves nothing.
+            let runs = vec![
+                (1, 0, t[0], true), (1, 1, t[3], true),
+                (2, 0, t[1], true), (2, 1, t[2], true),
+                (3, 0, 0, false),
+            ];
+            let scores = scores_from_runs(&runs, vec![TieBreaker::SolveTimes]);
+            let (team1, team2, team3) = (&scores[0], &scores[1], &scores[2]);
+            let order_ok = if t[0] < t[1] {
+                team1 < team2
+            } else if t[2] < t[3] {
+                team2 < team1
+            } else {
+                team1.same_rank(team2)
+            };
+            order_ok && team1 < team3 && team2 < team3
+        }
+
+        fn teams_without_solves_are_last_on_first_solve_time(time : u16, solves : bool) -> bool {
+            let runs = vec![(1, 0, time, solves), (2, 0, 0, false), (2, 1, u16::MAX, false)];
+            let scores = scores_from_runs(&runs, vec![TieBreaker::FirstSolveTime]);
+            if solves {
+                scores[0] < scores[1]
+            }
+            else {
+                scores[0].same_rank(&scores[1]) && scores[0] < scores[1]
+            }
+        }
+
+        fn score_order_is_deterministic(runs : Vec<(u8, u8, u16, bool)>, tie_breakers : Vec<TieBreaker>) -> bool {
+            let mut s1 = scores_from_runs(&runs, tie_breakers.clone());
+            let mut s2 = scores_from_runs(&runs, tie_breakers);
+            s1.sort();
+            s2.reverse();
+            s2.sort();
+            s1 == s2
+        }
+
         fn problem_with_runs_is_the_same_as_revealed(answers : Vec<Answer>) -> bool {
             let mut p1 = Problem::empty();
             let mut p2 = Problem::empty();
             
             println!("------------------------------");
             println!("answers={:?}", answers);
-            for a in &answers {
-                p1.add_run_problem(a.clone());
-                p2.add_run_frozen(a.clone());
+            for (id, a) in answers.iter().enumerate() {
+                p1.add_run_problem(a.clone(), id as i64);
+                p2.add_run_frozen(a.clone(), id as i64);
             }
             println!("p1={:?}", p1);
             while p2.wait() {
@@ -551,5 +1582,27 @@ mod tests {
 
             p1 == p2
         }
+
+        fn problem_penalty_follows_rules(answers : Vec<Answer>, penalty : u8) -> bool {
+            let mut p = Problem::empty();
+            for (id, a) in answers.iter().enumerate() {
+                p.add_run_problem(a.clone(), id as i64);
+            }
+
+            let mut rules = ScoringRules::new(penalty as i64);
+            rules.count_solve_time = false;
+
+            let wrong = answers
+                .iter()
+                .take_while(|a| matches!(a, Answer::No(_)))
+                .filter(|a| matches!(a, Answer::No(v) if v.penalized()))
+                .count() as i64;
+            if p.solved {
+                p.penalty(&rules) == wrong * penalty as i64
+            }
+            else {
+                p.penalty(&rules) == 0
+            }
+        }
     }
 }
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Answer, ContestFile, Minutes, ProblemInfo, RunTuple, Team, TimeFile, TimerData};
+
+/// The settings of the contest that are announced in the event feed.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ContestState {
+    pub contest_name: String,
+    pub maximum_time: Minutes,
+    pub score_freeze_time: Minutes,
+    pub answer_freeze_time: Option<Minutes>,
+}
+
+impl ContestState {
+    pub fn new(contest: &ContestFile) -> Self {
+        Self {
+            contest_name: contest.contest_name.clone(),
+            maximum_time: contest.maximum_time,
+            score_freeze_time: contest.score_freeze_time,
+            answer_freeze_time: contest.answer_freeze_time,
+        }
+    }
+}
+
+/// A change between two snapshots of the public data of the contest.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum ContestEvent {
+    Contest(ContestState),
+    /// A problem was added or changed.
+    Problem(ProblemInfo),
+    /// A team was added or changed.
+    Team(Team),
+    TeamRemoved(String),
+    Frozen,
+    Unfrozen,
+    NewRun(RunTuple),
+    /// A pending run got its verdict.
+    RunJudged(RunTuple),
+    /// A run changed after being judged, usually by a rejudge.
+    VerdictChanged { before: RunTuple, after: RunTuple },
+    RunDeleted(RunTuple),
+}
+
+impl ContestEvent {
+    /// Login of the team affected by the event, if any.
+    pub fn team_login(&self) -> Option<&str> {
+        match self {
+            ContestEvent::NewRun(r) | ContestEvent::RunJudged(r) | ContestEvent::RunDeleted(r) => Some(&r.team_login),
+            ContestEvent::VerdictChanged { after, .. } => Some(&after.team_login),
+            ContestEvent::Team(team) => Some(&team.login),
+            ContestEvent::TeamRemoved(login) => Some(login),
+            ContestEvent::Contest(_) | ContestEvent::Problem(_) | ContestEvent::Frozen | ContestEvent::Unfrozen => None,
+        }
+    }
+
+    /// Whether the event may change the scoreboard.
+    /// A run seen for the first time already judged counts, as polls far apart usually see them.
+    pub fn changes_score(&self) -> bool {
+        match self {
+            ContestEvent::NewRun(r) => r.answer != Answer::Wait,
+            ContestEvent::RunJudged(_) | ContestEvent::VerdictChanged { .. } | ContestEvent::RunDeleted(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// An event of the log, numbered from 1 in the order it was seen.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct EventRecord {
+    pub id: u64,
+    /// Contest time of the snapshot where the change was seen.
+    pub time: TimeFile,
+    pub event: ContestEvent,
+}
+
+/// Pushed to the displays every time the server refreshes its data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Update {
+    pub timer: TimerData,
+    /// Events seen in this refresh.
+    pub events: Vec<EventRecord>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Verdict;
+
+    fn run(answer: Answer) -> RunTuple {
+        RunTuple {
+            id: 1,
+            time: Minutes(10),
+            team_login: "teama".to_string(),
+            prob: "A".to_string(),
+            answer,
+        }
+    }
+
+    #[test]
+    fn test_changes_score() {
+        assert!(ContestEvent::NewRun(run(Answer::Yes(10))).changes_score());
+        assert!(ContestEvent::NewRun(run(Answer::No(Verdict::WrongAnswer))).changes_score());
+        assert!(!ContestEvent::NewRun(run(Answer::Wait)).changes_score());
+        assert!(ContestEvent::RunJudged(run(Answer::Yes(10))).changes_score());
+        assert!(!ContestEvent::Team(Team::new("teama", "A", "a")).changes_score());
+    }
+}
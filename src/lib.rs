@@ -1,6 +1,8 @@
 pub mod data;
-pub mod config;
 pub mod configdata;
+pub mod events;
+pub mod qualification;
+pub mod stats;
 
 #[cfg(test)]
 extern crate quickcheck;
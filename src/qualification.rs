@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::configdata::Contest;
+use crate::data::{ContestFile, Team};
+
+#[derive(Debug)]
+pub enum QualificationError {
+    UnknownSede(String),
+}
+
+impl std::error::Error for QualificationError {}
+
+impl fmt::Display for QualificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "QualificationError: {:?}", self)
+    }
+}
+
+/// Number of teams advanced directly by a sede.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SedeQuota {
+    pub sede: String,
+    pub teams: usize,
+}
+
+fn default_wildcard_sede() -> String {
+    "Brasil".to_string()
+}
+
+/// How teams advance to the next phase.
+/// The quotas are filled in order, then the wildcards from the ranking of `wildcard_sede`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualificationRules {
+    #[serde(default)]
+    pub quotas: Vec<SedeQuota>,
+    /// Maximum number of qualified teams of the same institution.
+    #[serde(default)]
+    pub school_cap: Option<usize>,
+    #[serde(default)]
+    pub wildcards: usize,
+    #[serde(default = "default_wildcard_sede")]
+    pub wildcard_sede: String,
+    /// Teams that solved fewer problems do not qualify.
+    #[serde(default)]
+    pub min_solved: usize,
+}
+
+impl Default for QualificationRules {
+    fn default() -> Self {
+        Self {
+            quotas: Vec::new(),
+            school_cap: None,
+            wildcards: 0,
+            wildcard_sede: default_wildcard_sede(),
+            min_solved: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum QualificationReason {
+    /// Qualified by the quota of the sede, with its local placement.
+    Sede { sede: String, placement: usize },
+    /// Qualified by a wildcard, with its placement in the wildcard sede.
+    Wildcard { placement: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Qualified {
+    pub team_login: String,
+    pub escola: String,
+    pub name: String,
+    pub reason: QualificationReason,
+}
+
+struct Selection<'a> {
+    rules: &'a QualificationRules,
+    contest: &'a ContestFile,
+    qualified: Vec<Qualified>,
+    logins: BTreeSet<String>,
+    per_school: BTreeMap<String, usize>,
+}
+
+impl<'a> Selection<'a> {
+    fn eligible(&self, team: &Team) -> bool {
+        let solved = team.problems.values().filter(|p| p.solved).count();
+        let school = self.per_school.get(&team.institution).cloned().unwrap_or(0);
+        team.official
+            && solved >= self.rules.min_solved
+            && !self.logins.contains(&team.login)
+            && self.rules.school_cap.map(|cap| school < cap).unwrap_or(true)
+    }
+
+    fn select(&mut self, team: &Team, reason: QualificationReason) {
+        *self.per_school.entry(team.institution.clone()).or_insert(0) += 1;
+        self.logins.insert(team.login.clone());
+        self.qualified.push(Qualified {
+            team_login: team.login.clone(),
+            escola: team.escola.clone(),
+            name: team.name.clone(),
+            reason,
+        });
+    }
+
+    /// Walks the ranking of the sede, selecting up to `slots` eligible teams.
+    fn fill<F>(&mut self, sedes: &Contest, source: &str, slots: usize, reason: F) -> Result<(), QualificationError>
+    where
+        F: Fn(usize) -> QualificationReason,
+    {
+        let sede = sedes
+            .sede(source)
+            .ok_or_else(|| QualificationError::UnknownSede(source.to_string()))?;
+        let ranking = sedes.ranking(sede, self.contest);
+
+        let mut taken = 0;
+        for entry in &ranking.entries {
+            if taken == slots {
+                break;
+            }
+            let team = match self.contest.teams.get(&entry.team_login) {
+                None => continue,
+                Some(t) => t,
+            };
+            if self.eligible(team) {
+                self.select(team, reason(entry.local_placement));
+                taken += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl QualificationRules {
+    /// Selects the teams that advance, in the order they were selected.
+    pub fn qualify(&self, sedes: &Contest, contest: &ContestFile) -> Result<Vec<Qualified>, QualificationError> {
+        let mut selection = Selection {
+            rules: self,
+            contest,
+            qualified: Vec::new(),
+            logins: BTreeSet::new(),
+            per_school: BTreeMap::new(),
+        };
+
+        for quota in &self.quotas {
+            selection.fill(sedes, &quota.sede, quota.teams, |placement| QualificationReason::Sede {
+                sede: quota.sede.clone(),
+                placement,
+            })?;
+        }
+
+        if self.wildcards > 0 {
+            selection.fill(sedes, &self.wildcard_sede, self.wildcards, |placement| {
+                QualificationReason::Wildcard { placement }
+            })?;
+        }
+
+        Ok(selection.qualified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configdata::Sede;
+    use crate::data::{Answer, Minutes, ProblemInfo, RunTuple, ScoringRules};
+
+    fn contest() -> ContestFile {
+        let teams = vec![
+            Team::new("teambrsp01", "USP", "a"),
+            Team::new("teambrsp02", "USP", "b"),
+            Team::new("teambrsp03", "UNICAMP", "c"),
+            Team::new("teambrsc01", "UFSC", "d"),
+            Team::new("teambrsc02", "UFSC", "e"),
+        ];
+        let mut contest = ContestFile::new(
+            "Teste".to_string(),
+            teams,
+            Minutes(0),
+            Minutes(300),
+            Minutes(240),
+            ScoringRules::default(),
+            ProblemInfo::numbered(3),
+        );
+        contest.recalculate_placement().unwrap();
+        contest
+    }
+
+    fn sedes() -> Contest {
+        Contest::new(vec![
+            Sede::new("São Paulo", "sp", "Brasil", "teambrsp"),
+            Sede::new("Santa Catarina", "sc", "Brasil", "teambrsc"),
+            Sede::new("Brasil", "Brasil", "Global", "teambr"),
+        ])
+    }
+
+    #[test]
+    fn quotas_then_wildcards() {
+        let rules = QualificationRules {
+            quotas: vec![
+                SedeQuota { sede: "sp".to_string(), teams: 1 },
+                SedeQuota { sede: "sc".to_string(), teams: 1 },
+            ],
+            wildcards: 2,
+            ..QualificationRules::default()
+        };
+        let q = rules.qualify(&sedes(), &contest()).unwrap();
+        let logins: Vec<_> = q.iter().map(|q| q.team_login.as_str()).collect();
+        assert_eq!(logins, vec!["teambrsp01", "teambrsc01", "teambrsc02", "teambrsp02"]);
+        assert_eq!(q[0].reason, QualificationReason::Sede { sede: "sp".to_string(), placement: 1 });
+        assert_eq!(q[2].reason, QualificationReason::Wildcard { placement: 2 });
+    }
+
+    #[test]
+    fn school_cap_skips_teams() {
+        let rules = QualificationRules {
+            quotas: vec![SedeQuota { sede: "sp".to_string(), teams: 2 }],
+            school_cap: Some(1),
+            ..QualificationRules::default()
+        };
+        let q = rules.qualify(&sedes(), &contest()).unwrap();
+        let logins: Vec<_> = q.iter().map(|q| q.team_login.as_str()).collect();
+        assert_eq!(logins, vec!["teambrsp01", "teambrsp03"]);
+    }
+
+    #[test]
+    fn min_solved_skips_teams() {
+        let mut contest = contest();
+        contest.apply_run(&RunTuple {
+            id: 1, time: Minutes(10), team_login: "teambrsp02".to_string(), prob: "A".to_string(), answer: Answer::Yes(10),
+        }).unwrap();
+        contest.recalculate_placement().unwrap();
+        let rules = QualificationRules {
+            quotas: vec![SedeQuota { sede: "sp".to_string(), teams: 2 }],
+            min_solved: 1,
+            ..QualificationRules::default()
+        };
+        let q = rules.qualify(&sedes(), &contest).unwrap();
+        let logins: Vec<_> = q.iter().map(|q| q.team_login.as_str()).collect();
+        assert_eq!(logins, vec!["teambrsp02"]);
+    }
+
+    #[test]
+    fn unknown_sede_is_an_error() {
+        let rules = QualificationRules {
+            quotas: vec![SedeQuota { sede: "xx".to_string(), teams: 2 }],
+            ..QualificationRules::default()
+        };
+        assert!(rules.qualify(&sedes(), &contest()).is_err());
+    }
+}
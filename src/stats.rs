@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::data::{Answer, ContestFile, RunsFile, Team};
+
+/// Length of the buckets of `solves_per_bucket`, in contest minutes.
+pub const BUCKET_MINUTES: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProblemStats {
+    pub label: String,
+    /// Judged runs.
+    pub attempts: usize,
+    /// Accepted runs.
+    pub accepted: usize,
+    /// Accepted runs over judged runs, zero without attempts.
+    pub acceptance_rate: f64,
+    /// Teams that solved the problem.
+    pub solved: usize,
+    pub first_solve: Option<i64>,
+    pub last_solve: Option<i64>,
+    /// Teams that solved the problem in each bucket of `BUCKET_MINUTES`.
+    pub solves_per_bucket: Vec<usize>,
+}
+
+/// Statistics of the visible problems, over a set of teams.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContestStats {
+    pub teams: usize,
+    pub problems: Vec<ProblemStats>,
+    /// Number of teams that solved exactly `i` problems, at index `i`.
+    pub teams_by_solved: Vec<usize>,
+}
+
+impl ContestStats {
+    /// Computes the statistics of the teams accepted by `filter`.
+    /// `contest` must have `runs` applied; pending runs, such as the ones
+    /// after the freeze in a public contest, are not counted as attempts.
+    pub fn new<F>(contest: &ContestFile, runs: &RunsFile, filter: F) -> Self
+    where
+        F: Fn(&Team) -> bool,
+    {
+        let teams: Vec<&Team> = contest.teams.values().filter(|t| filter(t)).collect();
+        let logins: BTreeSet<&String> = teams.iter().map(|t| &t.login).collect();
+        let buckets = ((contest.maximum_time.0 + BUCKET_MINUTES - 1) / BUCKET_MINUTES).max(1) as usize;
+
+        // judged and accepted runs, by problem
+        let mut judged: BTreeMap<&String, (usize, usize)> = BTreeMap::new();
+        for r in runs.sorted().iter().filter(|r| logins.contains(&r.team_login)) {
+            let counts = judged.entry(&r.prob).or_insert((0, 0));
+            match r.answer {
+                Answer::Yes(_) => {
+                    counts.0 += 1;
+                    counts.1 += 1;
+                }
+                Answer::No(_) => counts.0 += 1,
+                _ => (),
+            }
+        }
+
+        let problems = contest
+            .visible_problems()
+            .map(|info| {
+                let (attempts, accepted) = judged.get(&info.label).cloned().unwrap_or((0, 0));
+
+                let times: Vec<i64> = teams
+                    .iter()
+                    .filter_map(|t| t.problems.get(&info.label))
+                    .filter(|p| p.solved)
+                    .map(|p| p.time_solved)
+                    .collect();
+                let mut solves_per_bucket = vec![0; buckets];
+                for t in &times {
+                    let b = (t.max(&0) / BUCKET_MINUTES) as usize;
+                    solves_per_bucket[b.min(buckets - 1)] += 1;
+                }
+
+                ProblemStats {
+                    label: info.label.clone(),
+                    attempts,
+                    accepted,
+                    acceptance_rate: if attempts == 0 {
+                        0.0
+                    } else {
+                        accepted as f64 / attempts as f64
+                    },
+                    solved: times.len(),
+                    first_solve: times.iter().min().cloned(),
+                    last_solve: times.iter().max().cloned(),
+                    solves_per_bucket,
+                }
+            })
+            .collect();
+
+        let mut teams_by_solved = vec![0; contest.visible_problems().count() + 1];
+        for t in &teams {
+            let solved = t.problems.values().filter(|p| p.solved).count();
+            if solved >= teams_by_solved.len() {
+                teams_by_solved.resize(solved + 1, 0);
+            }
+            teams_by_solved[solved] += 1;
+        }
+
+        Self {
+            teams: teams.len(),
+            problems,
+            teams_by_solved,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Minutes, ProblemInfo, RunTuple, ScoringRules, Verdict};
+
+    fn run(id: i64, time: i64, team: &str, prob: &str, answer: Answer) -> RunTuple {
+        RunTuple { id, time: Minutes(time), team_login: team.to_string(), prob: prob.to_string(), answer }
+    }
+
+    #[test]
+    fn pending_runs_are_not_attempts() {
+        let teams = vec![Team::new("teama", "", ""), Team::new("teamb", "", ""), Team::new("teamc", "", "")];
+        let mut contest = ContestFile::new(
+            "Teste".to_string(), teams, Minutes(0), Minutes(300), Minutes(240), ScoringRules::default(), ProblemInfo::numbered(2));
+        let runs = RunsFile::new(vec![
+            run(1, 10, "teama", "A", Answer::No(Verdict::WrongAnswer)),
+            run(2, 20, "teama", "A", Answer::Yes(20)),
+            run(3, 95, "teamb", "A", Answer::Yes(95)),
+            run(4, 250, "teamb", "B", Answer::Wait),
+        ]);
+        for r in runs.sorted() {
+            contest.apply_run(r).unwrap();
+        }
+
+        let stats = ContestStats::new(&contest, &runs, |_| true);
+        assert_eq!(stats.teams, 3);
+        assert_eq!(stats.teams_by_solved, vec![1, 2, 0]);
+        let a = &stats.problems[0];
+        assert_eq!((a.attempts, a.accepted, a.solved), (3, 2, 2));
+        assert_eq!((a.first_solve, a.last_solve), (Some(20), Some(95)));
+        assert_eq!(a.solves_per_bucket, vec![1, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        let b = &stats.problems[1];
+        assert_eq!((b.attempts, b.solved, b.acceptance_rate), (0, 0, 0.0));
+
+        let only_a = ContestStats::new(&contest, &runs, |t| t.login == "teama");
+        assert_eq!(only_a.teams, 1);
+        assert_eq!(only_a.problems[0].attempts, 2);
+    }
+}
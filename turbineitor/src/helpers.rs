@@ -12,47 +12,121 @@ use std::collections::BTreeMap;
 use std::time::SystemTime;
 
 
-pub fn answer_from_code(c:i32, time:i64) -> data::Answer {
-    match c {
-        0 => data::Answer::Wait,
-        1 => data::Answer::Yes(time),
-        _ => data::Answer::No,
+pub fn verdict_from_text(text: &str) -> data::Verdict {
+    let text = text.to_lowercase();
+    if text.contains("compil") {
+        data::Verdict::CompilationError
+    }
+    else if text.contains("runtime") {
+        data::Verdict::RuntimeError
+    }
+    else if text.contains("time limit") || text.contains("time-limit") {
+        data::Verdict::TimeLimitExceeded
+    }
+    else if text.contains("memory") {
+        data::Verdict::MemoryLimitExceeded
+    }
+    else if text.contains("presentation") {
+        data::Verdict::PresentationError
+    }
+    else if text.contains("wrong") {
+        data::Verdict::WrongAnswer
+    }
+    else {
+        data::Verdict::Rejected
     }
 }
 
+pub fn answer_from_code(c:i32, time:data::Minutes, answers: &BTreeMap<i32, Answertable>) -> data::Answer {
+    match (c, answers.get(&c)) {
+        (0, _) => data::Answer::Wait,
+        (_, Some(a)) if a.yes => data::Answer::Yes(time.0),
+        (_, Some(a)) => data::Answer::No(verdict_from_text(&a.runanswer)),
+        (1, None) => data::Answer::Yes(time.0),
+        (_, None) => data::Answer::No(data::Verdict::Rejected),
+    }
+}
+
+pub fn get_answers(params: &Params, connection: &PgConnection) -> QueryResult<BTreeMap<i32, Answertable>> {
+    use self::answertable::dsl::*;
+
+    let mut t = BTreeMap::new();
+    for a in answertable
+    .filter(contestnumber.eq(params.contest_number))
+    .load::<Answertable>(connection)? {
+        t.insert(a.answernumber, a);
+    }
+
+    Ok(t)
+}
+
 pub fn to_run_tuple(r : &Runtable
     , letters :&BTreeMap<i32, String>
-    , teams:&BTreeMap<i32, data::Team>) -> Option<data::RunTuple> {
+    , teams:&BTreeMap<i32, data::Team>
+    , answers:&BTreeMap<i32, Answertable>
+    , rules: &data::ScoringRules) -> Option<data::RunTuple> {
     
     
-    let time = r.rundatediff as i64 / 60;
-
-    teams.get(&r.usernumber).map(|t|
-        data::RunTuple {
-            id : r.runnumber as i64,
-            time,
-            team_login : t.login.clone(),
-            prob : letters.get(&r.runproblem).unwrap().clone(),
-            answer : answer_from_code(r.runanswer, time),
-        }
-    )
+    let time = rules.time_rounding.minutes(data::Seconds(r.rundatediff as i64));
+
+    let t = teams.get(&r.usernumber)?;
+    Some(data::RunTuple {
+        id : r.runnumber as i64,
+        time,
+        team_login : t.login.clone(),
+        prob : letters.get(&r.runproblem)?.clone(),
+        answer : answer_from_code(r.runanswer, time, answers),
+    })
 }
 
-pub fn get_problem_letters(params: &Params, connection: &PgConnection) -> BTreeMap<i32, String> {
+pub fn get_problem_letters(params: &Params, connection: &PgConnection) -> QueryResult<BTreeMap<i32, String>> {
     use self::problemtable::dsl::*;
     
     let mut t = BTreeMap::new();
     for p in problemtable
     .filter(contestnumber.eq(params.contest_number))
-    .load::<Problemtable>(connection)
-    .expect("Error loading problem letters") {
+    .load::<Problemtable>(connection)? {
         t.insert(p.problemnumber, p.problemname);
     }
     
-    t
+    Ok(t)
+}
+
+fn problem_color(color: &Option<String>) -> Option<String> {
+    color.as_ref()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .map(|c| if c.starts_with('#') { c.to_string() } else { format!("#{}", c) })
+}
+
+pub fn get_problems(params: &Params, connection: &PgConnection) -> QueryResult<Vec<data::ProblemInfo>> {
+    use self::problemtable::dsl::*;
+
+    Ok(problemtable
+    .filter(contestnumber.eq(params.contest_number))
+    .order(problemnumber)
+    .load::<Problemtable>(connection)?
+    .iter()
+    .map(|p| data::ProblemInfo {
+        label: p.problemname.clone(),
+        name: p.problemfullname.clone().unwrap_or_else(|| p.problemname.clone()),
+        color: problem_color(&p.problemcolor),
+        hidden: p.fake,
+    })
+    .collect())
 }
 
-pub fn get_all_teams(params: &Params, connection: &PgConnection) -> BTreeMap<i32, data::Team> {
+/// BOCA descriptions usually look like `[SHORT] Long name`.
+fn split_institution(desc: &str) -> Option<(&str, &str)> {
+    let desc = desc.trim();
+    if !desc.starts_with('[') {
+        return None;
+    }
+    let end = desc.find(']')?;
+    Some((&desc[1..end], desc[end + 1..].trim()))
+}
+
+pub fn get_all_teams(params: &Params, connection: &PgConnection) -> QueryResult<BTreeMap<i32, data::Team>> {
     use self::usertable::dsl::*;
 
     let mut t = BTreeMap::new();
@@ -61,22 +135,30 @@ pub fn get_all_teams(params: &Params, connection: &PgConnection) -> BTreeMap<i32
     .filter(contestnumber.eq(params.contest_number))
     .filter(usersitenumber.eq(params.site_number))
     .filter(usertype.eq("team"))
-    .load::<Usertable>(connection)
-    .expect("Error loading users") {
-        t.insert(u.usernumber, data::Team::new(
-            &u.username,
-            "",
-            &u.userfullname,
-        ));
+    .load::<Usertable>(connection)? {
+        let desc = u.userdesc.clone().unwrap_or_default();
+        let (escola, institution) = match split_institution(&desc) {
+            Some((short, "")) => (short, short),
+            Some((short, long)) => (short, long),
+            None => {
+                let short = split_institution(&u.userfullname).map(|(s, _)| s).unwrap_or("");
+                (short, short)
+            },
+        };
+
+        let mut team = data::Team::new(&u.username, escola, &u.userfullname);
+        team.institution = institution.to_string();
+        t.insert(u.usernumber, team);
     }
 
-    t
+    Ok(t)
 }
 
-pub fn get_all_runs(params: &Params, connection: &PgConnection) -> data::RunsFile {
+pub fn get_all_runs(params: &Params, connection: &PgConnection, rules: &data::ScoringRules) -> QueryResult<data::RunsFile> {
     use self::runtable::dsl::*;
-    let letters = get_problem_letters(params, connection);
-    let teams = get_all_teams(params, connection);
+    let letters = get_problem_letters(params, connection)?;
+    let teams = get_all_teams(params, connection)?;
+    let answers = get_answers(params, connection)?;
 
 
     // teams.get(&r.usernumber).map(|t|
@@ -93,27 +175,28 @@ pub fn get_all_runs(params: &Params, connection: &PgConnection) -> data::RunsFil
         .filter(contestnumber.eq(params.contest_number))
         .filter(runsitenumber.eq(params.site_number))
         .select((runnumber, rundatediff, usernumber, runproblem, runanswer))
-        .load(connection)
-        .expect("Error loading runs");
+        .load(connection)?;
     
+    // runs of unknown teams or problems are left out
     let runs = res.iter()
-            .flat_map( |(id, time_large, team_id, prob_id, ans_id)| 
-            teams.get(&team_id).map (|t| {
-                let time = *time_large as i64 / 60;
-                data::RunTuple {
+            .flat_map( |(id, time_large, team_id, prob_id, ans_id)| {
+                let t = teams.get(&team_id)?;
+                let prob = letters.get(&prob_id)?;
+                let time = rules.time_rounding.minutes(data::Seconds(*time_large as i64));
+                Some(data::RunTuple {
                     id: *id as i64,
                     time,
                     team_login: t.login.clone(),
-                    prob: letters.get(&prob_id).unwrap().clone(),
-                    answer: answer_from_code(*ans_id, time)
-                }
-            })
+                    prob: prob.clone(),
+                    answer: answer_from_code(*ans_id, time, &answers)
+                })
+            }
         );
         // .load::<Runtable>(connection)
         // .iter()
         // .flat_map(|r| to_run_tuple(r, &letters, &teams));
 
-    data::RunsFile::new(runs.collect())
+    Ok(data::RunsFile::new(runs.collect()))
 
     // data::RunsFile {
     //     runs : runtable
@@ -127,42 +210,49 @@ pub fn get_all_runs(params: &Params, connection: &PgConnection) -> data::RunsFil
     // }
 }
 
-pub fn get_contest_file(params: & Params, connection: &PgConnection) -> data::ContestFile {
+/// The contest, and the current contest time in seconds.
+pub fn get_contest_file(params: & Params, connection: &PgConnection) -> QueryResult<(data::TimeFile, data::ContestFile)> {
     use self::contesttable::dsl::*;
 
-    let contest_opt = contesttable
+    let contest = contesttable
         .find(params.contest_number)
-        .load::<Contesttable>(connection)
-        .expect("Error loading contest");
-    let contest = contest_opt.first().unwrap();
+        .first::<Contesttable>(connection)?;
 
-    let number_problems = get_problem_letters(params, connection).len();
+    let problems = get_problems(params, connection)?;
 
-    let teams = get_all_teams(params, connection);
+    let teams = get_all_teams(params, connection)?;
 
     let current_time_now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
+        .unwrap_or_default()
         .as_secs() as i64;
 
     let contest_start_date = contest.conteststartdate as i64;
     let elapsed = current_time_now - contest_start_date;
 
 
-    let contest_duration = contest.contestduration as i64;
-    let current_time = contest_duration.min(elapsed);
+    let contest_duration = data::Seconds(contest.contestduration as i64);
+    let current_time = contest_duration.min(data::Seconds(elapsed));
 
     // println!("crazy times: {:?}", (current_time_now, contest_start_date, elapsed, contest_duration, current_time));
 
-    let score_freeze_time = contest.contestlastmilescore.unwrap_or(contest.contestduration);
+    let score_freeze_time = data::Seconds(contest.contestlastmilescore.unwrap_or(contest.contestduration) as i64);
     // let score_freeze_time = contest.contestduration;
-    data::ContestFile::new(
+    let scoring_rules = data::ScoringRules::new(contest.contestpenalty as i64 / 60);
+
+    let mut contest_file = data::ContestFile::new(
         contest.contestname.clone(),
         teams.values().cloned().collect(),
-        current_time / 60,
-        contest.contestduration as i64 / 60,
-        score_freeze_time as i64 / 60,
-        contest.contestpenalty as i64 / 60,
-        number_problems,
-    )
+        current_time.minutes(),
+        contest_duration.minutes(),
+        score_freeze_time.minutes(),
+        scoring_rules,
+        problems,
+    );
+    contest_file.answer_freeze_time = contest
+        .contestlastmileanswer
+        .map(|t| data::Seconds(t as i64))
+        .filter(|&t| t < contest_duration)
+        .map(|t| t.minutes());
+    Ok((current_time, contest_file))
 }
\ No newline at end of file
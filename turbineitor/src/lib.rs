@@ -8,8 +8,7 @@ use dotenv::dotenv;
 use std::env;
 
 use std::sync::Arc;
-use tokio;
-use tokio::{spawn, sync::Mutex};
+use tokio::sync::Mutex;
 
 pub mod models; 
 pub mod schema;
@@ -17,6 +16,9 @@ pub mod helpers;
 
 
 use lib_server::dataio::*;
+use lib_server::source::{ContestSource, PollConfig, SnapshotFuture};
+use maratona_animeitor_rust::configdata::ContestConfig;
+use maratona_animeitor_rust::data::ScoringRules;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Params {
@@ -25,51 +27,55 @@ pub struct Params {
     // pub connection: &'a PgConnection,
 }
 
-pub fn establish_connection() -> PgConnection {
+pub fn establish_connection() -> Result<PgConnection, ContestIOError> {
     dotenv().ok();
 
     let database_url = env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set");
+        .map_err(|e| ContestIOError::Info(format!("DATABASE_URL must be set: {}", e)))?;
     PgConnection::establish(&database_url)
-        .expect(&format!("Error connecting to {}", database_url))
+        .map_err(|e| ContestIOError::Info(format!("Error connecting to {}: {}", database_url, e)))
 }
 
-async fn update_runs(runs: Arc<Mutex<DB>>, params: Params) -> Result<(), ContestIOError> {
-
-    let connection = establish_connection();
-
-    let contest_data = helpers::get_contest_file(&params, &connection);
-    let runs_data = helpers::get_all_runs(&params, &connection);
-
-    let time_data = contest_data.current_time;
-
-    let mut db = runs.lock().await;
-    db.refresh_db(time_data, contest_data, runs_data)?;
-
-    Ok(())
+/// Reads the contest from the database of BOCA.
+pub struct BocaSource {
+    pub params: Params,
+    /// The scoring rules of the config, which replace the ones of BOCA,
+    /// so the runs are read with the configured time rounding.
+    pub scoring_rules: Option<ScoringRules>,
 }
 
+impl ContestSource for BocaSource {
+    fn fetch(&mut self) -> SnapshotFuture<'_> {
+        let params = self.params;
+        let scoring_rules = self.scoring_rules.clone();
+        Box::pin(async move {
+            // diesel blocks, so the queries run outside of the server threads
+            tokio::task::spawn_blocking(move || {
+                let connection = establish_connection()?;
+                let db_error = |e: diesel::result::Error| ContestIOError::Info(format!("Error reading BOCA: {}", e));
+                let (time_data, mut contest_data) = helpers::get_contest_file(&params, &connection).map_err(db_error)?;
+                if let Some(rules) = scoring_rules {
+                    contest_data.scoring_rules = rules;
+                }
+                let runs_data = helpers::get_all_runs(&params, &connection, &contest_data.scoring_rules).map_err(db_error)?;
+                Ok((time_data, contest_data, runs_data))
+            })
+            .await
+            .map_err(|e| ContestIOError::Info(format!("Error reading BOCA: {}", e)))?
+        })
+    }
+
+    fn name(&self) -> String {
+        format!("BOCA contest {} site {}", self.params.contest_number, self.params.site_number)
+    }
+}
 
-pub fn spawn_db_update(params: Params) -> Arc<Mutex<DB>> {
-    let shared_db = Arc::new(Mutex::new(DB::empty()));
-    let cloned_db = shared_db.clone();
-    spawn(async move {
-        let dur = tokio::time::Duration::new(30, 0);
-        let mut interval = tokio::time::interval(dur);
-        loop {
-            interval.tick().await;
-            let r = update_runs(cloned_db.clone(), params).await;
-            match r {
-                Ok(_) => (),
-                Err(e) => eprintln!("Error updating run: {}", e),
-            }
-        }
-    });
-    shared_db
+pub fn spawn_db_update(params: Params, config: ContestConfig) -> Arc<Mutex<DB>> {
+    lib_server::spawn_db_update(Box::new(BocaSource { params, scoring_rules: config.scoring_rules.clone() }), config, PollConfig::default())
 }
 
-pub async fn serve_simple_contest(server_port : u16, secret : &String, params: Params) {
+pub async fn serve_simple_contest(server_port : u16, secret : &String, params: Params, config : ContestConfig) {
 
-    let shared_db = spawn_db_update(params);
+    let shared_db = spawn_db_update(params, config);
     lib_server::serve_simple_contest_assets(shared_db, server_port, secret).await
 }
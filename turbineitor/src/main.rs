@@ -10,6 +10,8 @@ use std::env;
 use tokio;
 
 use lib_server::*;
+use lib_server::dataio::read_config;
+use maratona_animeitor_rust::configdata::ContestConfig;
 use turbineitor::Params;
 
 // fn main() {
@@ -43,8 +45,8 @@ use turbineitor::Params;
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 4 {
-        eprintln!("Expected 3 arguments: {:?}", args);
+    if args.len() != 4 && args.len() != 5 {
+        eprintln!("Expected 3 or 4 arguments: {:?}", args);
         return;
     }
     let server_port :u16= match args[1].parse() {
@@ -59,6 +61,13 @@ async fn main() {
         Ok(t) => t,
         Err(e) => panic!("Could not parse BOCA site_number {}", e),
     };
+    let config = match args.get(4) {
+        None => ContestConfig::default(),
+        Some(path) => match read_config(path) {
+            Ok(c) => c,
+            Err(e) => panic!("Could not read config file {}: {}", path, e),
+        },
+    };
 
     let secret = random_path_part();
 
@@ -91,6 +100,6 @@ async fn main() {
     
     let params = Params { contest_number, site_number };
     
-    turbineitor::serve_simple_contest(server_port, &secret, params).await;
+    turbineitor::serve_simple_contest(server_port, &secret, params, config).await;
 
 }
\ No newline at end of file